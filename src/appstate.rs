@@ -1,22 +1,35 @@
 use std::sync::Arc;
 
+use arc_swap::ArcSwap;
 use axum::extract::FromRef;
 
 use crate::{
     env::AppEnvVars,
-    imdb::{self, IMDB},
+    imdb::{IMDB, IMDBReady, IMDBShared},
+    reloader::spawn_reloader,
 };
 
 #[derive(FromRef, Clone)]
 pub struct AppState {
-    pub imdb: Arc<IMDB<imdb::ReadyState>>,
+    pub imdb: IMDBShared,
     pub env_vars: Arc<AppEnvVars>,
 }
 
+// Every request works on the database snapshot that was current when it came in.
+impl FromRef<AppState> for IMDBReady {
+    fn from_ref(state: &AppState) -> Self {
+        state.imdb.load_full()
+    }
+}
+
 impl AppState {
     pub async fn init() -> anyhow::Result<Self> {
         let env_vars = Arc::new(AppEnvVars::init()?);
-        let imdb = Arc::new(IMDB::init().await?);
+        let imdb = Arc::new(ArcSwap::from_pointee(IMDB::init(&env_vars).await?));
+
+        if env_vars.hot_reload {
+            spawn_reloader(imdb.clone(), env_vars.clone())?;
+        }
 
         Ok(Self { env_vars, imdb })
     }
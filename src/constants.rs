@@ -1 +1,2 @@
 pub const ERR_PFX: &str = "ERROR in";
+pub const WARN_PFX: &str = "WARNING in";
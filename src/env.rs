@@ -1,12 +1,22 @@
 use std::borrow::Cow;
+use std::path::PathBuf;
 
-use anyhow::Context;
+use crate::imdb::duplicates::DuplicatePolicy;
 
 #[derive(Clone)]
 pub struct AppEnvVars {
     pub host_address: Cow<'static, str>,
     pub host_origin: Cow<'static, str>,
     pub fe_dev_origin: Cow<'static, str>,
+    pub hot_reload: bool,
+    pub strict_ingestion: bool,
+    pub duplicate_policy: DuplicatePolicy,
+    pub data_roots: Vec<PathBuf>,
+    pub team_aliases_file: PathBuf,
+    pub csv_import_file: PathBuf,
+    pub timezones_file: PathBuf,
+    pub snapshot_file: Option<PathBuf>,
+    pub max_page_limit: usize,
 }
 
 use crate::constants::ERR_PFX;
@@ -14,9 +24,13 @@ const MOD: &str = "ENVVARS";
 
 impl AppEnvVars {
     pub fn init() -> anyhow::Result<Self> {
-        dotenv::dotenv().with_context(|| {
-            format!("{ERR_PFX} {MOD}: Could not read environment variables from '.env' file.")
-        })?;
+        // Variables can also come from the process environment,
+        // so the server can run from a directory without a '.env' file.
+        if let Err(err) = dotenv::dotenv() {
+            eprintln!(
+                "{ERR_PFX} {MOD}: Could not read environment variables from '.env' file: {err}. Continuing with process environment..."
+            );
+        }
 
         Ok(Self {
             host_address: Cow::from(
@@ -28,6 +42,72 @@ impl AppEnvVars {
             fe_dev_origin: Cow::from(
                 dotenv::var("FE_DEV_ORIGIN").unwrap_or("http://127.0.0.1:5173".to_string()),
             ),
+            hot_reload: dotenv::var("HOT_RELOAD")
+                .map(|value| value == "true" || value == "1")
+                .unwrap_or(false),
+            strict_ingestion: dotenv::var("STRICT_INGESTION")
+                .map(|value| value == "true" || value == "1")
+                .unwrap_or(false),
+            duplicate_policy: match dotenv::var("DUPLICATE_POLICY") {
+                Ok(value) => value.parse().unwrap_or_else(|err| {
+                    eprintln!("{ERR_PFX} {MOD}: {err} Continuing with keep_first...");
+                    DuplicatePolicy::default()
+                }),
+                Err(_) => DuplicatePolicy::default(),
+            },
+            // Comma separated list of data directories. Later ones take precedence.
+            data_roots: dotenv::var("DATA_ROOTS")
+                .unwrap_or("matchdata".to_string())
+                .split(',')
+                .map(|root| PathBuf::from(root.trim()))
+                .filter(|root| !root.as_os_str().is_empty())
+                .collect(),
+            team_aliases_file: PathBuf::from(
+                dotenv::var("TEAM_ALIASES_FILE").unwrap_or("team_aliases.json".to_string()),
+            ),
+            csv_import_file: PathBuf::from(
+                dotenv::var("CSV_IMPORT_FILE").unwrap_or("csv_import.json".to_string()),
+            ),
+            timezones_file: PathBuf::from(
+                dotenv::var("TIMEZONES_FILE").unwrap_or("tournament_timezones.json".to_string()),
+            ),
+            // An empty value turns snapshots off
+            snapshot_file: Some(
+                dotenv::var("SNAPSHOT_FILE").unwrap_or("imdb.snapshot".to_string()),
+            )
+            .filter(|file| !file.trim().is_empty())
+            .map(PathBuf::from),
+            max_page_limit: match dotenv::var("MAX_PAGE_LIMIT") {
+                Ok(value) => value.parse().unwrap_or_else(|err| {
+                    eprintln!(
+                        "{ERR_PFX} {MOD}: MAX_PAGE_LIMIT '{value}' is not a number: {err}. Continuing with 1000..."
+                    );
+                    1000
+                }),
+                Err(_) => 1000,
+            },
         })
     }
 }
+
+// Defaults with a single data root, for unit tests. Config files are looked for
+// in the data root, so they're left out unless a test writes them there.
+#[cfg(test)]
+impl AppEnvVars {
+    pub fn with_data_root(data_root: PathBuf) -> Self {
+        Self {
+            host_address: Cow::from("127.0.0.1:3000"),
+            host_origin: Cow::from("http://127.0.0.1:3000"),
+            fe_dev_origin: Cow::from("http://127.0.0.1:5173"),
+            hot_reload: false,
+            strict_ingestion: false,
+            duplicate_policy: DuplicatePolicy::default(),
+            team_aliases_file: data_root.join("team_aliases.json"),
+            csv_import_file: data_root.join("csv_import.json"),
+            timezones_file: data_root.join("tournament_timezones.json"),
+            data_roots: vec![data_root],
+            snapshot_file: None,
+            max_page_limit: 1000,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fmt::Display;
 use std::marker::PhantomData;
@@ -5,22 +6,50 @@ use std::sync::{Arc, atomic::{AtomicUsize, Ordering}};
 use std::time::Instant;
 
 use anyhow::Context;
+use arc_swap::ArcSwap;
+use bincode::{Decode, Encode};
 use chrono::Datelike;
 use rayon::prelude::*;
 
 pub mod data_types;
 mod json_fetcher;
+mod football_txt;
+mod csv_import;
+pub mod duplicates;
 mod db_api;
+mod db_diff;
+pub mod match_query;
+mod snapshot;
+pub mod ingestion_report;
+mod search_index;
+mod team_aliases;
+mod timezones;
+pub mod tournament_meta;
+mod tournament_name;
+pub mod round_info;
 
 use data_types::{
     Match, MatchId, MatchDataMap, MatchList, Season, SeasonId, SeasonMap, SeasonMatchMap, TournamentId,
     TournamentIdNameMap, TournamentNameIdMap, TournamentMatchMap, Year, YearlyMatchMap, TournamentSeasonMatchMap, TournamentYearlyMatchMap, TeamId,
-    TeamIdNameMap, TeamNameIdMap, TeamTournamentYearlyMatchMap, TeamTournamentSeasonMatchMap,
+    TeamIdNameMap, TeamNameIdMap, TeamTournamentYearlyMatchMap, TeamTournamentSeasonMatchMap, StatusMatchMap,
+    TeamIdAliasMap, TournamentMetaMap, DateMatchMap, TournamentIdCountryMap, TournamentPhaseMatchMap,
+    TournamentMatchdayMatchMap, PhaseMatchMap, MatchdayMatchMap, VenueId, VenueIdNameMap, VenueNameIdMap,
+    VenueMatchMap, Player, PlayerId, PlayerMap, PlayerKeyIdMap, PlayerMatchMap,
 };
+use csv_import::CsvImportConfig;
+use duplicates::{ParsedFolder, resolve_duplicates};
+use ingestion_report::{FailedIngestion, FolderReport, IngestionReport, IngestionStatus};
 use json_fetcher::fetch_json_raw_data;
-use json_fetcher::{JsonFileContentsRaw, JsonFilesContentsAllRaw};
-
-use crate::constants::ERR_PFX;
+use json_fetcher::{JsonFileContentsRaw, JsonFilesContentsAllRaw, source_stem};
+use search_index::SearchIndex;
+use team_aliases::TeamAliasResolver;
+use timezones::TimezoneTable;
+use tournament_meta::TournamentMeta;
+use tournament_name::TournamentName;
+use round_info::RoundInfo;
+
+use crate::constants::{ERR_PFX, WARN_PFX};
+use crate::env::AppEnvVars;
 const MOD: &str = "IMDB";
 
 #[derive(Debug)]
@@ -28,6 +57,7 @@ pub enum IMDBError {
     NoDataAvailable,
     DataIntegrity,
     FolderNameMalformed,
+    StrictIngestion(Vec<String>),
 }
 impl Error for IMDBError {}
 impl Display for IMDBError {
@@ -42,107 +72,217 @@ impl Display for IMDBError {
             FolderNameMalformed => {
                 write!(f, "Folder name should be in the format: 2015-16 or 2015.")
             }
+            StrictIngestion(failures) => {
+                write!(f, "Strict ingestion is on and these problems were found:")?;
+                for failure in failures {
+                    write!(f, "\n  - {failure}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
 
 pub trait IMDBState {}
+#[derive(Encode, Decode)]
 pub struct InitState;
+#[derive(Encode, Decode)]
 pub struct ReadyState;
 
 impl IMDBState for InitState {}
 impl IMDBState for ReadyState {}
 
 pub type IMDBReady = Arc<IMDB<ReadyState>>;
+// Shared handle that lets a rebuilt database be swapped in while requests run
+pub type IMDBShared = Arc<ArcSwap<IMDB<ReadyState>>>;
 
 #[allow(clippy::upper_case_acronyms)]
-pub struct IMDB<S: IMDBState = InitState> {
+#[derive(Encode, Decode)]
+pub struct IMDB<S: IMDBState> {
     season_id_head: SeasonId,
     tournament_id_head: TournamentId,
     team_id_head: TeamId,
+    venue_id_head: VenueId,
+    player_id_head: PlayerId,
+    #[bincode(with_serde)]
     match_id_head: AtomicUsize,
     season_map: SeasonMap,
     match_data_map: MatchDataMap,
     season_match_map: SeasonMatchMap,
     yearly_match_map: YearlyMatchMap,
+    #[bincode(with_serde)]
+    date_match_map: DateMatchMap,
     tournament_id_name_map: TournamentIdNameMap,
     tournament_name_id_map: TournamentNameIdMap,
+    tournament_id_country_map: TournamentIdCountryMap,
+    tournament_meta_map: TournamentMetaMap,
     tournament_match_map: TournamentMatchMap,
     tournament_season_match_map: TournamentSeasonMatchMap,
     tournament_yearly_match_map: TournamentYearlyMatchMap,
+    tournament_phase_match_map: TournamentPhaseMatchMap,
+    tournament_matchday_match_map: TournamentMatchdayMatchMap,
     team_id_name_map: TeamIdNameMap,
     team_name_id_map: TeamNameIdMap,
+    team_id_alias_map: TeamIdAliasMap,
+    team_alias_resolver: TeamAliasResolver,
+    timezone_table: TimezoneTable,
     team_tournament_season_match_map: TeamTournamentSeasonMatchMap,
     team_tournament_yearly_match_map: TeamTournamentYearlyMatchMap,
     team_home_tournament_season_match_map: TeamTournamentSeasonMatchMap,
     team_away_tournament_season_match_map: TeamTournamentSeasonMatchMap,
     team_home_tournament_yearly_match_map: TeamTournamentYearlyMatchMap,
     team_away_tournament_yearly_match_map: TeamTournamentYearlyMatchMap,
+    status_match_map: StatusMatchMap,
+    venue_id_name_map: VenueIdNameMap,
+    venue_name_id_map: VenueNameIdMap,
+    venue_match_map: VenueMatchMap,
+    player_map: PlayerMap,
+    player_key_id_map: PlayerKeyIdMap,
+    player_match_map: PlayerMatchMap,
+    search_index: SearchIndex,
+    ingestion_report: IngestionReport,
     _phantom: PhantomData<S>,
 }
 
 impl IMDB<InitState> {
-    pub async fn init() -> anyhow::Result<IMDB<ReadyState>> {
-        let raw_data = fetch_json_raw_data().await?;
+    pub async fn init(env_vars: &AppEnvVars) -> anyhow::Result<IMDB<ReadyState>> {
+        let mut report = IngestionReport::default();
+        let raw_data = match fetch_json_raw_data(&env_vars.data_roots, &mut report).await {
+            Ok(raw_data) => raw_data,
+            Err(err) => {
+                // Skipped data roots are only in the report, so they're listed instead
+                report.finish();
+                let err = match env_vars.strict_ingestion {
+                    true => Self::strict_ingestion_check(&report).err().unwrap_or(err),
+                    false => err,
+                };
+                return Err(Self::failed_ingestion(err, report));
+            }
+        };
+
+        // Building is skipped when a snapshot of the same sources exists.
+        // Roots, folders and files the fetch left out aren't in the raw data, so the fetch
+        // report counts as a source too. The report of a loaded snapshot then starts out
+        // the same as the one of this run would.
+        let fetch_report = serde_json::to_string(&report)?;
+        let checksum = snapshot::source_checksum(
+            &raw_data,
+            &[&env_vars.team_aliases_file, &env_vars.csv_import_file, &env_vars.timezones_file],
+            &format!("{:?}{fetch_report}", env_vars.duplicate_policy),
+        ).await;
+        if let Some(snapshot_file) = &env_vars.snapshot_file
+            && let Some(me) = IMDB::<ReadyState>::load_snapshot(snapshot_file, checksum).await
+        {
+            if env_vars.strict_ingestion && let Err(err) = Self::strict_ingestion_check(&me.ingestion_report) {
+                return Err(Self::failed_ingestion(err, me.ingestion_report));
+            }
+            return Ok(me);
+        }
+
+        let team_alias_resolver = TeamAliasResolver::init(&env_vars.team_aliases_file, &mut report).await;
+        let csv_config = CsvImportConfig::init(&env_vars.csv_import_file, &mut report).await;
+        let timezone_table = TimezoneTable::init(&env_vars.timezones_file, &mut report).await;
 
         let me = Self {
             season_id_head: 1,
             tournament_id_head: 1,
             match_id_head: AtomicUsize::new(1),
             team_id_head: 1,
+            venue_id_head: 1,
+            player_id_head: 1,
             season_map: SeasonMap::new(),
             match_data_map: MatchDataMap::new(),
             season_match_map: SeasonMatchMap::new(),
             yearly_match_map: YearlyMatchMap::new(),
+            date_match_map: DateMatchMap::new(),
             tournament_id_name_map: TournamentIdNameMap::new(),
             tournament_name_id_map: TournamentNameIdMap::new(),
+            tournament_id_country_map: TournamentIdCountryMap::new(),
+            tournament_meta_map: TournamentMetaMap::new(),
             tournament_match_map: TournamentMatchMap::new(),
             tournament_season_match_map: TournamentSeasonMatchMap::new(),
             tournament_yearly_match_map: TournamentYearlyMatchMap::new(),
+            tournament_phase_match_map: TournamentPhaseMatchMap::new(),
+            tournament_matchday_match_map: TournamentMatchdayMatchMap::new(),
             team_id_name_map: TeamIdNameMap::new(),
             team_name_id_map: TeamNameIdMap::new(),
+            team_id_alias_map: TeamIdAliasMap::new(),
+            team_alias_resolver,
+            timezone_table,
             team_tournament_season_match_map: TeamTournamentSeasonMatchMap::new(),
             team_tournament_yearly_match_map: TeamTournamentYearlyMatchMap::new(),
             team_home_tournament_season_match_map: TeamTournamentSeasonMatchMap::new(),
             team_away_tournament_season_match_map: TeamTournamentSeasonMatchMap::new(),
             team_home_tournament_yearly_match_map: TeamTournamentYearlyMatchMap::new(),
             team_away_tournament_yearly_match_map: TeamTournamentYearlyMatchMap::new(),
+            status_match_map: StatusMatchMap::new(),
+            venue_id_name_map: VenueIdNameMap::new(),
+            venue_name_id_map: VenueNameIdMap::new(),
+            venue_match_map: VenueMatchMap::new(),
+            player_map: PlayerMap::new(),
+            player_key_id_map: PlayerKeyIdMap::new(),
+            player_match_map: PlayerMatchMap::new(),
+            search_index: SearchIndex::default(),
+            ingestion_report: report,
             _phantom: PhantomData,
         };
 
-        let me = Self::build(me, raw_data)?;
+        let me = Self::build(me, raw_data, &csv_config, env_vars)?;
+
+        if let Some(snapshot_file) = &env_vars.snapshot_file {
+            me.write_snapshot(snapshot_file, checksum).await;
+        }
 
         Ok(me)
     }
 
-    fn build(mut me: Self, raw_data: JsonFilesContentsAllRaw) -> anyhow::Result<IMDB<ReadyState>> {
+    fn build(mut me: Self, raw_data: JsonFilesContentsAllRaw, csv_config: &CsvImportConfig, env_vars: &AppEnvVars) -> anyhow::Result<IMDB<ReadyState>> {
         const ERR_FN: &str = "::build";
 
         println!("{MOD}: building in memory database.");
         let now = Instant::now();
 
+        // Kept out of `me` while building, so it can be written next to the indexes
+        let mut report = std::mem::take(&mut me.ingestion_report);
+        let mut parsed = vec![];
+
         for (folder, files) in raw_data.into_iter() {
+            let folder_report = report.folder(&folder);
             let season_result = Self::per_folder_season(&folder, &mut me.season_map, &mut me.season_id_head);
 
             let Ok(season_id) = season_result else {
-                eprintln!(
-                    "{ERR_PFX} {MOD}{ERR_FN}: Could not parse folder name '{folder}' as a season: {} is the reason. Continuing without this folder...",
-                    season_result.unwrap_err()
-                );
+                let message = format!("Could not parse folder name '{folder}' as a season: {} is the reason.", season_result.unwrap_err());
+                eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing without this folder...");
+                folder_report.error(message);
                 continue;
             };
+            folder_report.season_id = Some(season_id);
 
-            let mut matchlists = Self::per_folder_parse_contents_as_matchlists(
+            // It should be safe to unwrap, the season was just created.
+            let season_start_year = me.season_map.get(&season_id).unwrap().start_year;
+
+            let match_lists = Self::per_folder_parse_contents_as_matchlists(
                 &me.match_id_head,
                 &folder,
                 &files,
-                season_id,
+                (season_id, season_start_year),
+                csv_config,
+                folder_report,
             );
 
+            parsed.push(ParsedFolder { folder, season_id, match_lists });
+        }
+
+        // Duplicates can be in different folders, so all of them are parsed before indexing
+        resolve_duplicates(&mut parsed, &me.team_alias_resolver, env_vars.duplicate_policy, &mut report);
+
+        for ParsedFolder { folder, season_id, match_lists: mut matchlists } in parsed.into_iter() {
+            let folder_report = report.folder(&folder);
+
             if matchlists.is_empty() {
-                eprintln!(
-                    "{ERR_PFX} {ERR_FN}: Could not parse any matches from '{folder}'. Continuing without this folder..."
-                );
+                let message = format!("Could not parse any matches from '{folder}'.");
+                eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing without this folder...");
+                folder_report.error(message);
                 continue;
             }
 
@@ -163,11 +303,13 @@ impl IMDB<InitState> {
             matchlists
                 .into_iter()
                 .for_each(|match_list| {
-                    Self::per_matchlist_build(
+                    let file_report = folder_report.file(&match_list.file_name);
+                    let warnings = Self::per_matchlist_build(
                         match_list,
                         season_id,
                         &mut me,
                     );
+                    warnings.into_iter().for_each(|warning| file_report.warning(warning));
                 });
         }
 
@@ -176,15 +318,47 @@ impl IMDB<InitState> {
             now.elapsed().as_millis()
         );
 
+        report.finish();
+
+        // Checked before the hard errors below, so that every offending file gets listed
+        if env_vars.strict_ingestion && let Err(err) = Self::strict_ingestion_check(&report) {
+            return Err(Self::failed_ingestion(err, report));
+        }
+
         if me.match_data_map.is_empty() {
-            return Err(IMDBError::NoDataAvailable.into());
+            return Err(Self::failed_ingestion(IMDBError::NoDataAvailable.into(), report));
         }
 
-        Self::check_data_integrity(&me)?;
+        if let Err(err) = Self::check_data_integrity(&me) {
+            return Err(Self::failed_ingestion(err, report));
+        }
+        me.ingestion_report = report;
 
         Ok(Self::ready(me))
     }
 
+    // The report goes along with the error of a failed build. Strict failures are
+    // in it already, other fatal errors are added so it tells why nothing got loaded.
+    fn failed_ingestion(err: anyhow::Error, mut report: IngestionReport) -> anyhow::Error {
+        if !matches!(err.downcast_ref::<IMDBError>(), Some(IMDBError::StrictIngestion(_))) {
+            report.error(format!("{err:#}"));
+        }
+        report.status = IngestionStatus::Failed;
+
+        err.context(FailedIngestion(report))
+    }
+
+    // In strict mode anything that was skipped while ingesting aborts the build
+    fn strict_ingestion_check(report: &IngestionReport) -> anyhow::Result<()> {
+        let failures = report.failures();
+
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(IMDBError::StrictIngestion(failures).into())
+        }
+    }
+
     fn check_data_integrity(me: &Self) -> anyhow::Result<()> {
         let hashmap_len = me.match_data_map.len();
 
@@ -314,6 +488,28 @@ impl IMDB<InitState> {
                             )
                     )
             );
+
+        let tournament_phase_len = me
+            .tournament_phase_match_map
+            .iter()
+            .fold(0,
+                |acc, item| acc + item.1
+                    .iter()
+                    .fold(0,
+                        |acc, item| acc + item.1.len()
+                    )
+            );
+
+        let status_len = me
+            .status_match_map
+            .iter()
+            .fold(0, |acc, item| acc + item.1.len());
+
+        let date_len = me
+            .date_match_map
+            .iter()
+            .fold(0, |acc, item| acc + item.1.len());
+
         if hashmap_len != yearly_len ||
             hashmap_len != season_len ||
             hashmap_len != tournament_len ||
@@ -324,7 +520,10 @@ impl IMDB<InitState> {
             hashmap_len != team_home_tournament_season_len ||
             hashmap_len != team_away_tournament_season_len ||
             hashmap_len != team_home_tournament_yearly_len ||
-            hashmap_len != team_away_tournament_yearly_len
+            hashmap_len != team_away_tournament_yearly_len ||
+            hashmap_len != tournament_phase_len ||
+            hashmap_len != status_len ||
+            hashmap_len != date_len
         {
             return Err(IMDBError::DataIntegrity).with_context(|| {
                 format!(
@@ -340,6 +539,9 @@ impl IMDB<InitState> {
                     Team away tournament season map length: {}\n\
                     Team home tournament yearly map length: {}\n\
                     Team away tournament yearly map length: {}\n\
+                    Tournament phase map length: {}\n\
+                    Status map length: {}\n\
+                    Date map length: {}\n\
                     one of these lengths is wrong.",
                     hashmap_len,
                     yearly_len,
@@ -352,12 +554,15 @@ impl IMDB<InitState> {
                     team_home_tournament_season_len,
                     team_away_tournament_season_len,
                     team_home_tournament_yearly_len,
-                    team_away_tournament_yearly_len
+                    team_away_tournament_yearly_len,
+                    tournament_phase_len,
+                    status_len,
+                    date_len
                 )
             });
         }
 
-        println!("{MOD}: {} matches from {} tournaments with {} teams added to the database.", hashmap_len, me.tournament_id_name_map.len(), me.team_id_name_map.len());
+        println!("{MOD}: {} matches from {} tournaments with {} teams at {} venues added to the database.", hashmap_len, me.tournament_id_name_map.len(), me.team_id_name_map.len(), me.venue_id_name_map.len());
 
         Ok(())
     }
@@ -368,59 +573,93 @@ impl IMDB<InitState> {
             tournament_id_head,
             match_id_head,
             team_id_head,
+            venue_id_head,
+            player_id_head,
             season_map,
             match_data_map,
             season_match_map,
             yearly_match_map,
+            date_match_map,
             tournament_id_name_map,
             tournament_name_id_map,
+            tournament_id_country_map,
+            tournament_meta_map,
             tournament_match_map,
             tournament_season_match_map,
             tournament_yearly_match_map,
+            tournament_phase_match_map,
+            tournament_matchday_match_map,
             team_id_name_map,
             team_name_id_map,
+            team_id_alias_map,
+            team_alias_resolver,
+            timezone_table,
             team_tournament_season_match_map,
             team_tournament_yearly_match_map,
             team_home_tournament_season_match_map,
             team_away_tournament_season_match_map,
             team_home_tournament_yearly_match_map,
             team_away_tournament_yearly_match_map,
+            status_match_map,
+            venue_id_name_map,
+            venue_name_id_map,
+            venue_match_map,
+            player_map,
+            player_key_id_map,
+            player_match_map,
+            search_index: _,
+            ingestion_report,
             _phantom,
         } = me;
 
+        // Names are final only once every match is in
+        let search_index = SearchIndex::build(&team_id_name_map, &team_id_alias_map, &tournament_id_name_map, &tournament_id_country_map, &tournament_meta_map);
+
         IMDB::<ReadyState> {
             season_id_head,
             tournament_id_head,
             match_id_head,
             team_id_head,
+            venue_id_head,
+            player_id_head,
             season_map,
             match_data_map,
             season_match_map,
             yearly_match_map,
+            date_match_map,
             tournament_id_name_map,
             tournament_name_id_map,
+            tournament_id_country_map,
+            tournament_meta_map,
             tournament_match_map,
             tournament_season_match_map,
             tournament_yearly_match_map,
+            tournament_phase_match_map,
+            tournament_matchday_match_map,
             team_id_name_map,
             team_name_id_map,
+            team_id_alias_map,
+            team_alias_resolver,
+            timezone_table,
             team_tournament_season_match_map,
             team_tournament_yearly_match_map,
             team_home_tournament_season_match_map,
             team_away_tournament_season_match_map,
             team_home_tournament_yearly_match_map,
             team_away_tournament_yearly_match_map,
+            status_match_map,
+            venue_id_name_map,
+            venue_name_id_map,
+            venue_match_map,
+            player_map,
+            player_key_id_map,
+            player_match_map,
+            search_index,
+            ingestion_report,
             _phantom: PhantomData,
         }
     }
 
-    fn get_tournament_name(matchlist_name: &str) -> String {
-        let mut name_split = matchlist_name.split(" ").collect::<Vec<_>>();
-        let _ = name_split.pop();
-
-        name_split.join(" ")
-    }
-
     fn per_folder_season(
         folder: &str,
         season_map: &mut SeasonMap,
@@ -448,11 +687,15 @@ impl IMDB<InitState> {
                 let mut end_year = None;
                 if let Some(ey) = folder_split.get(1) {
                     end_year = if let Ok(ey) = ey.parse::<Year>() {
-                        // We don't check for the edge case where start_year is 2999 and end_year
-                        // should be 3000, because end_year data in json files have two digits.
-                        let year_millenia = (start_year / 1000) * 1000;
+                        // End year has two digits, so it's completed from the century of the start year.
+                        // Seasons like 1999-00 roll over into the next century.
+                        let year_century = (start_year / 100) * 100;
 
-                        let full_ey = ey + year_millenia;
+                        let full_ey = if ey + year_century < start_year {
+                            ey + year_century + 100
+                        } else {
+                            ey + year_century
+                        };
 
                         Some(full_ey)
                     } else {
@@ -486,24 +729,59 @@ impl IMDB<InitState> {
         id_head: &AtomicUsize,
         folder: &str,
         files: &JsonFileContentsRaw,
-        season_id: SeasonId,
+        season: (SeasonId, Year),
+        csv_config: &CsvImportConfig,
+        folder_report: &mut FolderReport,
     ) -> Vec<MatchList> {
         const ERR_FN: &str = "::per_folder_parse_contents_as_matchlists";
+        let (season_id, season_start_year) = season;
+
+        let superseded = Self::superseded_source_files(files);
+        for (fname, kept) in superseded.iter() {
+            let message = format!("Left out, '{kept}' has the same matches and is loaded instead.");
+            eprintln!("{WARN_PFX} {MOD}{ERR_FN}: '{folder}/{fname}': {message}");
+            folder_report.file(fname).warning(message);
+        }
 
-        files.par_iter().filter_map(|(fname, contents)| {
-            let result: Result<MatchList, _> = serde_json::from_str(contents);
+        let results: Vec<_> = files.par_iter().filter(|(fname, _)| !superseded.contains_key(fname.as_str())).map(|(fname, contents)| {
+            // Football.TXT source files and csv result files are parsed next to the json files
+            let result: anyhow::Result<MatchList> = if fname.ends_with(".txt") {
+                football_txt::parse_match_list(contents, season_start_year)
+            } else if fname.ends_with(".csv") {
+                csv_config.parse_match_list(fname, contents)
+            } else {
+                serde_json::from_str(contents).map_err(Into::into)
+            };
+
+            (fname, result.map(|mut list| {
+                list.file_name = fname.clone();
+                list.resolve_statuses();
+                list.matches.iter_mut().for_each(|mch| {
+                    mch.id = id_head.fetch_add(1, Ordering::AcqRel);
+                    mch.season_id = season_id;
+                });
+
+                list
+            }))
+        }).collect();
+
+        // Reported in file order after the parallel part
+        results.into_iter().filter_map(|(fname, result)| {
+            let file_report = folder_report.file(fname);
 
             match result {
                 Ok(mut list) => {
-                    list.matches.iter_mut().for_each(|mch| {
-                        mch.id = id_head.fetch_add(1, Ordering::AcqRel);
-                        mch.season_id = season_id;
-                    });
+                    file_report.matches = list.matches.len();
+                    for warning in list.warnings.drain(..) {
+                        eprintln!("{WARN_PFX} {MOD}{ERR_FN}: '{folder}/{fname}': {warning}");
+                        file_report.warning(warning);
+                    }
 
                     Some(list)
                 },
                 Err(err) => {
                     eprintln!("{ERR_PFX} {MOD}{ERR_FN}: Error while parsing file '{folder}/{fname}': {err} Continuing without it...");
+                    file_report.error(format!("Error while parsing file: {err}"));
 
                     None
                 }
@@ -511,15 +789,40 @@ impl IMDB<InitState> {
         }).collect()
     }
 
+    // Football.TXT and csv files are often the sources of a json export next to them,
+    // like 'en.1.txt' and 'en.1.json'. Only one file of a name is parsed, json before txt
+    // before csv. The fetcher already left out the ones of earlier data roots, so these
+    // are all from the same root. Returns the files left out with the file loaded instead.
+    fn superseded_source_files(files: &JsonFileContentsRaw) -> BTreeMap<&str, &str> {
+        let mut preferred: BTreeMap<&str, (usize, &str)> = BTreeMap::new();
+        for fname in files.keys() {
+            if let Some((stem, rank)) = source_stem(fname) {
+                let best = preferred.entry(stem).or_insert((rank, fname));
+                if rank < best.0 {
+                    *best = (rank, fname);
+                }
+            }
+        }
+
+        files.keys()
+            .filter_map(|fname| {
+                let (stem, _) = source_stem(fname)?;
+                let (_, kept) = preferred[stem];
+                (kept != fname.as_str()).then_some((fname.as_str(), kept))
+            })
+            .collect()
+    }
+
     fn per_folder_season_match_map(match_ids: Vec<MatchId>, season_id: SeasonId) -> SeasonMatchMap {
         SeasonMatchMap::from([(season_id, match_ids)])
     }
 
+    // Returns warnings about the match list to be added to the ingestion report
     fn per_matchlist_build(
         match_list: MatchList,
         season_id: SeasonId,
         me: &mut Self,
-    ) {
+    ) -> Vec<String> {
         // It should be safe to unwrap the season here
         // Because we got the ID from the upper loop that creates the season.
         let season = me.season_map.get(&season_id).unwrap();
@@ -553,7 +856,7 @@ impl IMDB<InitState> {
                 start_year,
                 maybe_end_year
             )
-        );
+        )
     }
 
     fn per_matchlist_tournament_maps(
@@ -562,18 +865,46 @@ impl IMDB<InitState> {
         season_id: SeasonId,
         match_ids: (&[MatchId], &[MatchId], &[MatchId]),
         years: (Year, Option<Year>)
-    ) {
+    ) -> Vec<String> {
+        const ERR_FN: &str = "::per_matchlist_tournament_maps";
         let (match_ids_start, match_ids_end, match_ids_all) = match_ids;
         let (start_year, maybe_end_year) = years;
+        let mut warnings = vec![];
+
+        let tour_name = TournamentName::parse(&match_list.name);
+        if let Some(warning) = Self::check_season_label(&tour_name, &match_list, years) {
+            warnings.push(warning);
+        }
 
-        let tour_name = Self::get_tournament_name(&match_list.name);
-        let tournament_id = if let Some(tournament_id) = me.tournament_name_id_map.get(&tour_name) {
+        let tour_key = tour_name.key();
+        let tournament_id = if let Some(tournament_id) = me.tournament_name_id_map.get(&tour_key) {
             *tournament_id
         } else {
             let tournament_id = me.tournament_id_head;
 
-            me.tournament_name_id_map.insert(tour_name.clone(), tournament_id);
-            me.tournament_id_name_map.insert(tournament_id, tour_name);
+            // Tournament metadata comes from the file name the tournament is first seen in.
+            match TournamentMeta::from_file_name(&match_list.file_name) {
+                Some(mut meta) => {
+                    meta.timezone = Some(me.timezone_table.resolve(Some(&meta)).name().to_string());
+                    me.tournament_meta_map.insert(tournament_id, meta);
+                }
+                None => {
+                    let message = format!(
+                        "Could not derive tournament metadata from file name '{}' of tournament '{tour_key}'.",
+                        match_list.file_name
+                    );
+                    eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing without it...");
+                    warnings.push(message);
+                }
+            }
+
+            let TournamentName { country, competition, .. } = tour_name;
+
+            if let Some(country) = country {
+                me.tournament_id_country_map.insert(tournament_id, country);
+            }
+            me.tournament_name_id_map.insert(tour_key, tournament_id);
+            me.tournament_id_name_map.insert(tournament_id, competition);
 
             me.tournament_id_head += 1;
 
@@ -585,8 +916,15 @@ impl IMDB<InitState> {
             .and_modify(|list| list.extend_from_slice(match_ids_all))
             .or_insert(Vec::from(match_ids_all));
 
+        let competition_kind = me.tournament_meta_map.get(&tournament_id).map(|meta| meta.kind);
+        let timezone = me.timezone_table.resolve(me.tournament_meta_map.get(&tournament_id));
+
         match_list.matches.into_iter().for_each(|mut mch| {
             mch.tournament_id = tournament_id;
+            mch.kickoff = timezones::kickoff(mch.date, mch.time, timezone);
+            mch.round_info = RoundInfo::parse(mch.round.as_deref(), mch.stage.as_deref(), competition_kind);
+            Self::per_match_round_maps(me, &mch, tournament_id);
+            Self::per_match_venue_maps(me, &mut mch);
             Self::per_match_team_maps(
                 me,
                 &mch,
@@ -595,6 +933,15 @@ impl IMDB<InitState> {
                 start_year,
                 maybe_end_year,
             );
+            Self::per_match_goal_maps(me, &mut mch);
+            me.status_match_map
+                .entry(mch.status)
+                .and_modify(|list| list.push(mch.id))
+                .or_insert(vec![mch.id]);
+            me.date_match_map
+                .entry(mch.date)
+                .and_modify(|list| list.push(mch.id))
+                .or_insert(vec![mch.id]);
             me.match_data_map.insert(mch.id, mch);
         });
 
@@ -629,6 +976,37 @@ impl IMDB<InitState> {
                 })
                 .or_insert(YearlyMatchMap::from([(end_year, Vec::from(match_ids_end))]));
         }
+
+        warnings
+    }
+
+    // Season label in the tournament name should agree with the season of the folder it's in.
+    // A mismatch doesn't stop ingestion, we go with the folder season.
+    fn check_season_label(tour_name: &TournamentName, match_list: &MatchList, years: (Year, Option<Year>)) -> Option<String> {
+        const ERR_FN: &str = "::check_season_label";
+        let (start_year, maybe_end_year) = years;
+
+        let season = tour_name.season.as_ref()?;
+
+        if season.start_year == start_year && season.end_year == maybe_end_year {
+            return None;
+        }
+
+        let label_years = |start: Year, end: Option<Year>| match end {
+            Some(end) => format!("{start}-{end}"),
+            None => start.to_string(),
+        };
+
+        let message = format!(
+            "Season label '{}' in tournament name '{}' of file '{}' does not match folder season '{}'.",
+            label_years(season.start_year, season.end_year),
+            match_list.name,
+            match_list.file_name,
+            label_years(start_year, maybe_end_year),
+        );
+        eprintln!("{WARN_PFX} {MOD}{ERR_FN}: {message}");
+
+        Some(message)
     }
 
     fn per_matchlist_yearly_map(
@@ -642,6 +1020,110 @@ impl IMDB<InitState> {
             .or_insert(match_ids.to_vec());
     }
 
+    fn per_match_round_maps(me: &mut Self, mch: &Match, tournament_id: TournamentId) {
+        let phase_key = (mch.round_info.phase, mch.round_info.round_of);
+
+        me.tournament_phase_match_map
+            .entry(tournament_id)
+            .and_modify(|map| {
+                map
+                    .entry(phase_key)
+                    .and_modify(|list| list.push(mch.id))
+                    .or_insert(vec![mch.id]);
+            })
+            .or_insert(PhaseMatchMap::from([(phase_key, vec![mch.id])]));
+
+        if let Some(matchday) = mch.round_info.matchday {
+            me.tournament_matchday_match_map
+                .entry(tournament_id)
+                .and_modify(|map| {
+                    map
+                        .entry(matchday)
+                        .and_modify(|list| list.push(mch.id))
+                        .or_insert(vec![mch.id]);
+                })
+                .or_insert(MatchdayMatchMap::from([(matchday, vec![mch.id])]));
+        }
+    }
+
+    // Venues are only known by name, so the same ground spelled differently is two venues
+    fn per_match_venue_maps(me: &mut Self, mch: &mut Match) {
+        let Some(venue_name) = mch.venue.as_deref().map(str::trim).filter(|name| !name.is_empty()) else {
+            return;
+        };
+
+        let venue_id = if let Some(venue_id) = me.venue_name_id_map.get(venue_name) {
+            *venue_id
+        } else {
+            let venue_id = me.venue_id_head;
+
+            me.venue_name_id_map.insert(venue_name.to_string(), venue_id);
+            me.venue_id_name_map.insert(venue_id, venue_name.to_string());
+
+            me.venue_id_head += 1;
+
+            venue_id
+        };
+
+        mch.venue_id = Some(venue_id);
+        me.venue_match_map
+            .entry(venue_id)
+            .and_modify(|list| list.push(mch.id))
+            .or_insert(vec![mch.id]);
+    }
+
+    // Goals of both teams are merged into one list in the order they were scored.
+    // Team ids have to be in the team maps already.
+    fn per_match_goal_maps(me: &mut Self, mch: &mut Match) {
+        if mch.goals1.is_empty() && mch.goals2.is_empty() {
+            return;
+        }
+
+        // It should be safe to unwrap, both teams were just added
+        let team_ids = [
+            *me.team_name_id_map.get(&mch.team1).unwrap(),
+            *me.team_name_id_map.get(&mch.team2).unwrap(),
+        ];
+
+        let goals1 = std::mem::take(&mut mch.goals1).into_iter().map(|goal| (1, goal));
+        let goals2 = std::mem::take(&mut mch.goals2).into_iter().map(|goal| (2, goal));
+
+        for (team, mut goal) in goals1.chain(goals2) {
+            let scorer_team_id = if goal.own_goal {
+                team_ids[2 - team as usize]
+            } else {
+                team_ids[team as usize - 1]
+            };
+
+            let player_key = (scorer_team_id, goal.player.trim().to_string());
+            let player_id = if let Some(player_id) = me.player_key_id_map.get(&player_key) {
+                *player_id
+            } else {
+                let player_id = me.player_id_head;
+
+                me.player_map.insert(player_id, Player { id: player_id, name: player_key.1.clone(), team_id: scorer_team_id });
+                me.player_key_id_map.insert(player_key, player_id);
+
+                me.player_id_head += 1;
+
+                player_id
+            };
+
+            goal.player_id = player_id;
+            goal.team = team;
+
+            let match_list = me.player_match_map.entry(player_id).or_default();
+            if match_list.last() != Some(&mch.id) {
+                match_list.push(mch.id);
+            }
+
+            mch.goals.push(goal);
+        }
+
+        // Goals without a minute go last
+        mch.goals.sort_by_key(|goal| (goal.minute.is_none(), goal.minute, goal.offset));
+    }
+
     fn per_match_team_maps(
         me: &mut Self,
         mch: &Match,
@@ -650,17 +1132,34 @@ impl IMDB<InitState> {
         start_year: Year,
         maybe_end_year: Option<Year>,
     ) {
-        let team_names = [mch.team1.clone(), mch.team2.clone()];
+        let team_names = [mch.team1.as_str(), mch.team2.as_str()];
         for (index, team_name) in team_names.into_iter().enumerate() {
-            let team_id = if let Some(team_id) = me.team_name_id_map.get(&team_name) {
+            let team_id = if let Some(team_id) = me.team_name_id_map.get(team_name) {
                 *team_id
             } else {
-                let team_id = me.team_id_head;
+                // Name variants of the same club are folded into one canonical team
+                let canonical_name = me.team_alias_resolver.resolve(team_name);
+
+                let team_id = if let Some(team_id) = me.team_name_id_map.get(canonical_name) {
+                    *team_id
+                } else {
+                    let team_id = me.team_id_head;
 
-                me.team_name_id_map.insert(team_name.clone(), team_id);
-                me.team_id_name_map.insert(team_id, team_name);
+                    me.team_name_id_map.insert(canonical_name.to_string(), team_id);
+                    me.team_id_name_map.insert(team_id, canonical_name.to_string());
 
-                me.team_id_head += 1;
+                    me.team_id_head += 1;
+
+                    team_id
+                };
+
+                if team_name != canonical_name {
+                    me.team_name_id_map.insert(team_name.to_string(), team_id);
+                    me.team_id_alias_map
+                        .entry(team_id)
+                        .or_default()
+                        .insert(team_name.to_string());
+                }
 
                 team_id
             };
@@ -726,3 +1225,23 @@ impl IMDB<InitState> {
         }
     }
 }
+
+// Databases for unit tests, built from data files written to a temporary data root
+#[cfg(test)]
+impl IMDB<ReadyState> {
+    pub(crate) fn from_test_files(root_name: &str, folders: &[(&str, &[(&str, &str)])]) -> Self {
+        let root = std::env::temp_dir().join(format!("football-emporium-{root_name}-{}", std::process::id()));
+        for (folder, files) in folders {
+            std::fs::create_dir_all(root.join(folder)).unwrap();
+            for (file_name, contents) in files.iter() {
+                std::fs::write(root.join(folder).join(file_name), contents).unwrap();
+            }
+        }
+
+        let env_vars = AppEnvVars::with_data_root(root.clone());
+        let result = tokio::runtime::Runtime::new().unwrap().block_on(IMDB::init(&env_vars));
+        std::fs::remove_dir_all(&root).unwrap();
+
+        result.unwrap()
+    }
+}
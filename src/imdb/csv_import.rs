@@ -0,0 +1,355 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Display;
+use std::path::Path;
+
+use chrono::{NaiveDate, NaiveTime};
+use serde::Deserialize;
+
+use super::data_types::{Match, MatchList, MatchStatus, Score, ScoreRaw};
+use super::ingestion_report::IngestionReport;
+use super::json_fetcher::load_optional_config;
+
+use crate::constants::ERR_PFX;
+
+const MOD: &str = "CSV_IMPORT";
+
+#[derive(Debug)]
+pub enum CsvImportError {
+    ColumnNotFound(String),
+    NoMatchesFound,
+}
+impl Display for CsvImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use CsvImportError::*;
+        match self {
+            ColumnNotFound(column) => write!(f, "Required column '{column}' not found in header."),
+            NoMatchesFound => write!(f, "No usable match rows found."),
+        }
+    }
+}
+impl Error for CsvImportError {}
+
+// Header names of the columns a match is read from.
+// Defaults follow the football-data.co.uk format.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CsvColumns {
+    pub date: String,
+    pub time: Option<String>,
+    pub team1: String,
+    pub team2: String,
+    pub ft_goals1: String,
+    pub ft_goals2: String,
+    pub ht_goals1: Option<String>,
+    pub ht_goals2: Option<String>,
+    pub round: Option<String>,
+    pub venue: Option<String>,
+}
+
+impl Default for CsvColumns {
+    fn default() -> Self {
+        Self {
+            date: "Date".to_string(),
+            time: Some("Time".to_string()),
+            team1: "HomeTeam".to_string(),
+            team2: "AwayTeam".to_string(),
+            ft_goals1: "FTHG".to_string(),
+            ft_goals2: "FTAG".to_string(),
+            ht_goals1: Some("HTHG".to_string()),
+            ht_goals2: Some("HTAG".to_string()),
+            round: None,
+            venue: None,
+        }
+    }
+}
+
+// Import config file format. Every field is optional.
+// Csv files carry no tournament name, so it's looked up from `tournament_names`
+// with the file name without extension, like 'en.1' for 'en.1.csv'.
+// Files without an entry use that file code as the name.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct CsvImportConfig {
+    pub delimiter: char,
+    // Tried in order until one parses. Two digit year formats should come first,
+    // because '%Y' also accepts a two digit year like '24' as is.
+    pub date_formats: Vec<String>,
+    pub time_format: String,
+    pub columns: CsvColumns,
+    pub tournament_names: BTreeMap<String, String>,
+}
+
+impl Default for CsvImportConfig {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            date_formats: vec!["%d/%m/%y".to_string(), "%d/%m/%Y".to_string()],
+            time_format: "%H:%M".to_string(),
+            columns: CsvColumns::default(),
+            tournament_names: BTreeMap::new(),
+        }
+    }
+}
+
+impl CsvImportConfig {
+    pub async fn init(config_file: &Path, report: &mut IngestionReport) -> Self {
+        const ERR_FN: &str = "::init";
+
+        // Config file is optional. Without it the football-data.co.uk defaults are used.
+        let mut config =
+            load_optional_config::<Self>(config_file, "csv import config file", report).await;
+
+        // Csv delimiters are single bytes
+        if !config.delimiter.is_ascii() {
+            let message = format!(
+                "Delimiter '{}' in csv import config file '{}' is not an ascii character.",
+                config.delimiter,
+                config_file.display()
+            );
+            eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing with ','...");
+            report.warning(message);
+            config.delimiter = Self::default().delimiter;
+        }
+
+        config
+    }
+
+    // Parses a csv results file into the same structure the json files are deserialized into.
+    // Rows without a usable date or team names, like the empty trailing rows
+    // some exports have, are skipped. Rows without full time goals are unplayed fixtures.
+    pub fn parse_match_list(&self, file_name: &str, contents: &str) -> anyhow::Result<MatchList> {
+        // Excel exports often start with a byte order mark that would end up in the first header
+        let contents = contents.trim_start_matches('\u{feff}');
+
+        // Delimiter was checked to be ascii when the config was loaded
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(self.delimiter as u8)
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(contents.as_bytes());
+
+        let headers = reader.headers()?.clone();
+        let required = |column: &str| {
+            headers
+                .iter()
+                .position(|header| header == column)
+                .ok_or_else(|| CsvImportError::ColumnNotFound(column.to_string()))
+        };
+        let optional = |column: &Option<String>| {
+            column
+                .as_ref()
+                .and_then(|column| headers.iter().position(|header| header == column))
+        };
+
+        let columns = &self.columns;
+        let date_idx = required(&columns.date)?;
+        let team1_idx = required(&columns.team1)?;
+        let team2_idx = required(&columns.team2)?;
+        let ft_idx = [required(&columns.ft_goals1)?, required(&columns.ft_goals2)?];
+        let ht_idx = optional(&columns.ht_goals1).zip(optional(&columns.ht_goals2));
+        let time_idx = optional(&columns.time);
+        let round_idx = optional(&columns.round);
+        let venue_idx = optional(&columns.venue);
+
+        let mut matches = vec![];
+        let mut skipped_rows = 0;
+
+        for record in reader.records() {
+            let Ok(record) = record else {
+                skipped_rows += 1;
+                continue;
+            };
+            let field = |idx: usize| record.get(idx).filter(|value| !value.is_empty());
+
+            let (Some(date), Some(team1), Some(team2)) = (
+                field(date_idx).and_then(|date| self.parse_date(date)),
+                field(team1_idx),
+                field(team2_idx),
+            ) else {
+                skipped_rows += 1;
+                continue;
+            };
+
+            let goals = |[idx1, idx2]: [usize; 2]| {
+                Some([field(idx1)?.parse().ok()?, field(idx2)?.parse().ok()?])
+            };
+
+            let raw = ScoreRaw {
+                ht: ht_idx.and_then(|(idx1, idx2)| goals([idx1, idx2])),
+                ft: goals(ft_idx),
+                et: None,
+                p: None,
+            };
+
+            matches.push(Match {
+                id: 0,
+                season_id: 0,
+                tournament_id: 0,
+                round: round_idx.and_then(field).map(str::to_string),
+                date,
+                time: time_idx
+                    .and_then(field)
+                    .and_then(|time| NaiveTime::parse_from_str(time, &self.time_format).ok()),
+                kickoff: None,
+                team1: team1.to_string(),
+                team2: team2.to_string(),
+                score: Score::from(raw),
+                stage: None,
+                venue: venue_idx.and_then(field).map(str::to_string),
+                venue_id: None,
+                goals1: vec![],
+                goals2: vec![],
+                goals: vec![],
+                status_raw: None,
+                status: MatchStatus::Regular,
+                round_info: Default::default(),
+            });
+        }
+
+        if matches.is_empty() {
+            return Err(CsvImportError::NoMatchesFound.into());
+        }
+
+        let code = file_name.strip_suffix(".csv").unwrap_or(file_name);
+        let name = self
+            .tournament_names
+            .get(code)
+            .cloned()
+            .unwrap_or_else(|| code.to_string());
+
+        let mut warnings = vec![];
+        if skipped_rows > 0 {
+            warnings.push(format!(
+                "{skipped_rows} rows were not usable and were skipped."
+            ));
+        }
+
+        Ok(MatchList {
+            file_name: String::new(),
+            warnings,
+            name,
+            matches,
+        })
+    }
+
+    fn parse_date(&self, date: &str) -> Option<NaiveDate> {
+        self.date_formats
+            .iter()
+            .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imdb::data_types::ScoreGoals;
+
+    const RESULTS_FILE: &str = "\u{feff}Div,Date,Time,HomeTeam,AwayTeam,FTHG,FTAG,HTHG,HTAG
+E0,16/08/2024,20:00,Man United,Fulham,1,0,0,0
+E0,17/08/24,12:30,Ipswich,Liverpool,0,2,,
+E0,18/08/2024,,Arsenal,Wolves,,,,
+,,,,,,,,
+";
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn maps_the_default_columns() {
+        let config = CsvImportConfig {
+            tournament_names: BTreeMap::from([(
+                "en.1".to_string(),
+                "English Premier League 2024/25".to_string(),
+            )]),
+            ..Default::default()
+        };
+        let list = config.parse_match_list("en.1.csv", RESULTS_FILE).unwrap();
+
+        assert_eq!(list.name, "English Premier League 2024/25");
+        assert_eq!(list.warnings.len(), 1);
+        assert_eq!(list.matches.len(), 3);
+
+        let first = &list.matches[0];
+        assert_eq!(first.date, date(2024, 8, 16));
+        assert_eq!(first.time, NaiveTime::from_hms_opt(20, 0, 0));
+        assert_eq!(
+            (first.team1.as_str(), first.team2.as_str()),
+            ("Man United", "Fulham")
+        );
+        assert_eq!(first.score.full_time, Some(ScoreGoals(1, 0)));
+        assert_eq!(first.score.half_time, Some(ScoreGoals(0, 0)));
+
+        let second = &list.matches[1];
+        assert_eq!(second.score.full_time, Some(ScoreGoals(0, 2)));
+        assert_eq!(second.score.half_time, None);
+
+        // Rows without goals are fixtures still to be played
+        let third = &list.matches[2];
+        assert_eq!(third.time, None);
+        assert_eq!(third.score.full_time, None);
+    }
+
+    #[test]
+    fn maps_configured_columns() {
+        let config: CsvImportConfig = serde_json::from_str(
+            r#"{
+                "delimiter": ";",
+                "date_formats": ["%Y-%m-%d"],
+                "columns": {
+                    "date": "Datum",
+                    "team1": "Heim",
+                    "team2": "Gast",
+                    "ft_goals1": "T1",
+                    "ft_goals2": "T2",
+                    "round": "Spieltag"
+                }
+            }"#,
+        )
+        .unwrap();
+        let contents = "Spieltag;Datum;Heim;Gast;T1;T2\n1;2024-08-23;Bayern;Wolfsburg;3;2\n";
+        let list = config.parse_match_list("de.1.csv", contents).unwrap();
+
+        assert_eq!(list.name, "de.1");
+        assert!(list.warnings.is_empty());
+
+        let mch = &list.matches[0];
+        assert_eq!(mch.date, date(2024, 8, 23));
+        assert_eq!(mch.round.as_deref(), Some("1"));
+        assert_eq!(
+            (mch.team1.as_str(), mch.team2.as_str()),
+            ("Bayern", "Wolfsburg")
+        );
+        assert_eq!(mch.score.full_time, Some(ScoreGoals(3, 2)));
+    }
+
+    #[test]
+    fn two_digit_years_are_tried_first() {
+        let config = CsvImportConfig::default();
+        assert_eq!(config.parse_date("17/08/24"), Some(date(2024, 8, 17)));
+        assert_eq!(config.parse_date("17/08/2024"), Some(date(2024, 8, 17)));
+        assert_eq!(config.parse_date("2024-08-17"), None);
+    }
+
+    #[test]
+    fn missing_columns_and_rows_are_errors() {
+        let config = CsvImportConfig::default();
+
+        let err = config
+            .parse_match_list("en.1.csv", "Date,HomeTeam,FTHG,FTAG\n")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CsvImportError>(),
+            Some(CsvImportError::ColumnNotFound(column)) if column == "AwayTeam"
+        ));
+
+        let err = config
+            .parse_match_list("en.1.csv", "Date,HomeTeam,AwayTeam,FTHG,FTAG\n")
+            .unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<CsvImportError>(),
+            Some(CsvImportError::NoMatchesFound)
+        ));
+    }
+}
@@ -1,28 +1,52 @@
-use chrono::{NaiveDate, NaiveTime};
+use bincode::{Decode, Encode};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use super::round_info::{Matchday, Phase, RoundInfo, RoundOf};
+use super::tournament_meta::TournamentMeta;
 
 pub type MatchId = usize;
 pub type TournamentId = usize;
 pub type SeasonId = usize;
 pub type TeamId = usize;
+pub type VenueId = usize;
+pub type PlayerId = usize;
 pub type SeasonMap = BTreeMap<SeasonId, Season>;
 pub type SeasonMatchMap = BTreeMap<SeasonId, Vec<MatchId>>;
 pub type Year = u32;
 pub type MatchDataMap = HashMap<MatchId, Match>;
 pub type YearlyMatchMap = BTreeMap<Year, Vec<MatchId>>;
+pub type DateMatchMap = BTreeMap<NaiveDate, Vec<MatchId>>;
 pub type TournamentIdNameMap = HashMap<TournamentId, String>;
 pub type TournamentNameIdMap = HashMap<String, TournamentId>;
+pub type TournamentIdCountryMap = HashMap<TournamentId, String>;
+pub type TournamentMetaMap = HashMap<TournamentId, TournamentMeta>;
 pub type TournamentMatchMap = BTreeMap<TournamentId, Vec<MatchId>>;
 pub type TournamentSeasonMatchMap = BTreeMap<TournamentId, SeasonMatchMap>;
 pub type TournamentYearlyMatchMap = BTreeMap<TournamentId, YearlyMatchMap>;
+pub type StatusMatchMap = BTreeMap<MatchStatus, Vec<MatchId>>;
+pub type PhaseKey = (Phase, Option<RoundOf>);
+pub type PhaseMatchMap = BTreeMap<PhaseKey, Vec<MatchId>>;
+pub type TournamentPhaseMatchMap = BTreeMap<TournamentId, PhaseMatchMap>;
+pub type MatchdayMatchMap = BTreeMap<Matchday, Vec<MatchId>>;
+pub type TournamentMatchdayMatchMap = BTreeMap<TournamentId, MatchdayMatchMap>;
 
 pub type TeamIdNameMap = BTreeMap<TeamId, String>;
 pub type TeamNameIdMap = BTreeMap<String, TeamId>;
+pub type TeamIdAliasMap = BTreeMap<TeamId, BTreeSet<String>>;
 pub type TeamTournamentSeasonMatchMap = BTreeMap<TeamId, TournamentSeasonMatchMap>;
 pub type TeamTournamentYearlyMatchMap = BTreeMap<TeamId, TournamentYearlyMatchMap>;
 
-#[derive(Debug, Serialize)]
+pub type VenueIdNameMap = BTreeMap<VenueId, String>;
+pub type VenueNameIdMap = HashMap<String, VenueId>;
+pub type VenueMatchMap = BTreeMap<VenueId, Vec<MatchId>>;
+
+pub type PlayerMap = BTreeMap<PlayerId, Player>;
+pub type PlayerKeyIdMap = HashMap<(TeamId, String), PlayerId>;
+pub type PlayerMatchMap = BTreeMap<PlayerId, Vec<MatchId>>;
+
+#[derive(Debug, Serialize, Encode, Decode)]
 pub struct Season {
     pub id: SeasonId,
     pub start_year: Year,
@@ -33,6 +57,8 @@ pub struct Season {
 pub struct Tournament<'a> {
     pub id: TournamentId,
     pub name: &'a str,
+    pub country: Option<&'a str>,
+    pub meta: Option<&'a TournamentMeta>,
 }
 
 #[derive(Debug, Serialize, PartialEq, Eq, PartialOrd, Ord)]
@@ -41,7 +67,140 @@ pub struct Team<'a> {
     pub name: &'a str,
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+// Teams that played home matches at the venue, more than one is a shared ground.
+#[derive(Debug, Serialize)]
+pub struct Venue<'a> {
+    pub id: VenueId,
+    pub name: &'a str,
+    pub matches: usize,
+    pub home_teams: Vec<Team<'a>>,
+}
+
+// Players are only known by name, so they're told apart by the team they scored for.
+// The same name at two teams is two players.
+#[derive(Debug, Serialize, Encode, Decode)]
+pub struct Player {
+    pub id: PlayerId,
+    pub name: String,
+    pub team_id: TeamId,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Scorer<'a> {
+    pub player: &'a Player,
+    pub team: Team<'a>,
+    pub goals: usize,
+    pub penalties: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlayerMatchGoals<'a> {
+    #[serde(rename = "match")]
+    pub mch: &'a Match,
+    pub goals: Vec<&'a Goal>,
+}
+
+// Own goals are listed but don't count for the player's total.
+#[derive(Debug, Serialize)]
+pub struct PlayerGoals<'a> {
+    pub player: &'a Player,
+    pub team: Team<'a>,
+    pub goals: usize,
+    pub own_goals: usize,
+    pub list: Vec<PlayerMatchGoals<'a>>,
+}
+
+// One team's record against the other. Only matches with a full time score count.
+#[derive(Debug, Serialize)]
+pub struct HeadToHeadSide<'a> {
+    pub team: Team<'a>,
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+    pub goals_for: usize,
+    pub goals_against: usize,
+    // Widest margin first, then most goals scored, then the earliest
+    pub biggest_win: Option<&'a Match>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct HeadToHead<'a> {
+    pub played: usize,
+    pub sides: [HeadToHeadSide<'a>; 2],
+    pub first_meeting: Option<&'a Match>,
+    pub last_meeting: Option<&'a Match>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FormResult {
+    Win,
+    Draw,
+    Loss,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormMatch {
+    pub match_id: MatchId,
+    pub date: NaiveDate,
+    pub result: FormResult,
+}
+
+// Place in the league table of the season, counting the matches played on earlier days
+#[derive(Debug, Serialize)]
+pub struct LeaguePosition {
+    pub position: usize,
+    pub played: usize,
+    pub points: usize,
+    pub goal_difference: isize,
+}
+
+// Form is the latest matches before this one in any tournament, oldest first.
+// Position is only there for league matches once the team has played.
+#[derive(Debug, Serialize)]
+pub struct MatchTeamContext<'a> {
+    pub team: Team<'a>,
+    pub form: Vec<FormMatch>,
+    pub position: Option<LeaguePosition>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MatchDetail<'a> {
+    #[serde(rename = "match")]
+    pub mch: &'a Match,
+    pub tournament: Tournament<'a>,
+    pub season: &'a Season,
+    pub teams: [MatchTeamContext<'a>; 2],
+    pub previous_meeting: Option<&'a Match>,
+    pub next_meeting: Option<&'a Match>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize, Encode, Decode)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchKind {
+    Team,
+    Tournament,
+}
+
+// Alias is the one the search matched, when it wasn't the name itself.
+// Country is there for tournaments, their names alone aren't unique.
+#[derive(Debug, Serialize)]
+pub struct SearchHit<'a> {
+    pub kind: SearchKind,
+    pub id: usize,
+    pub name: &'a str,
+    pub country: Option<&'a str>,
+    pub alias: Option<&'a str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TeamAliases<'a> {
+    pub id: TeamId,
+    pub name: &'a str,
+    pub aliases: Vec<&'a str>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Encode, Decode)]
 pub struct Match {
     #[serde(skip_deserializing)]
     pub id: MatchId,
@@ -50,44 +209,224 @@ pub struct Match {
     #[serde(skip_deserializing)]
     pub tournament_id: TournamentId,
     pub round: Option<String>,
+    #[bincode(with_serde)]
     pub date: NaiveDate,
+    #[bincode(with_serde)]
     pub time: Option<NaiveTime>,
+    // Date and time in the timezone of the tournament
+    #[serde(skip_deserializing)]
+    #[bincode(with_serde)]
+    pub kickoff: Option<DateTime<FixedOffset>>,
     pub team1: String,
     pub team2: String,
     pub score: Score,
     pub stage: Option<String>,
+    // openfootball json calls the venue 'ground'
+    #[serde(alias = "ground")]
+    pub venue: Option<String>,
+    #[serde(skip_deserializing)]
+    pub venue_id: Option<VenueId>,
+    // Goals scored for each team as they're in the json data,
+    // moved into `goals` when the match is indexed
+    #[serde(default, skip_serializing)]
+    pub goals1: Vec<Goal>,
+    #[serde(default, skip_serializing)]
+    pub goals2: Vec<Goal>,
+    #[serde(skip_deserializing)]
+    pub goals: Vec<Goal>,
+    // Status as it's in the json data, resolved into `status` when the file is parsed
+    #[serde(default, rename(deserialize = "status"), skip_serializing)]
+    pub status_raw: Option<String>,
+    #[serde(skip_deserializing)]
+    pub status: MatchStatus,
+    #[serde(skip_deserializing)]
+    pub round_info: RoundInfo,
+}
+
+// Names a match the same way across rebuilds of the database, unlike its id,
+// which depends on the order the files were parsed in.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MatchKey {
+    pub date: NaiveDate,
+    pub tournament: String,
+    pub team1: String,
+    pub team2: String,
+}
+
+// Goal event in the openfootball json format, like
+// { "name": "Kane", "minute": 45, "offset": 2, "penalty": true }.
+// Own goals are listed under the team they count for, so the scorer plays for the other team.
+#[derive(Debug, Clone, Deserialize, Serialize, Encode, Decode)]
+pub struct Goal {
+    #[serde(skip_deserializing)]
+    pub player_id: PlayerId,
+    #[serde(rename(deserialize = "name"))]
+    pub player: String,
+    // 1 when the goal counts for team1, 2 for team2
+    #[serde(skip_deserializing)]
+    pub team: u8,
+    pub minute: Option<u8>,
+    // Added time, like the 2 of 45+2'
+    pub offset: Option<u8>,
+    #[serde(default)]
+    pub penalty: bool,
+    #[serde(default, rename(deserialize = "owngoal"))]
+    pub own_goal: bool,
+}
+
+// Matches without a status field in the json data are regular ones.
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Deserialize,
+    Serialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Encode,
+    Decode,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchStatus {
+    #[default]
+    Regular,
+    Postponed,
+    Cancelled,
+    Awarded,
+    Abandoned,
+    // Statuses in the data that aren't any of the above
+    Unknown,
+}
+
+impl MatchStatus {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "regular" => Some(Self::Regular),
+            "postponed" => Some(Self::Postponed),
+            "cancelled" => Some(Self::Cancelled),
+            "awarded" => Some(Self::Awarded),
+            "abandoned" => Some(Self::Abandoned),
+            "unknown" => Some(Self::Unknown),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
 pub struct MatchList {
+    #[serde(skip)]
+    pub file_name: String,
+    // Problems the source parsers could work around, for the ingestion report
+    #[serde(skip)]
+    pub warnings: Vec<String>,
     pub name: String,
     pub matches: Vec<Match>,
 }
 
+impl MatchList {
+    // An unknown status doesn't fail the whole file, only its match is kept as unknown.
+    pub fn resolve_statuses(&mut self) {
+        for mch in self.matches.iter_mut() {
+            let Some(status_raw) = mch.status_raw.take() else {
+                continue;
+            };
+
+            mch.status = MatchStatus::from_name(&status_raw).unwrap_or_else(|| {
+                self.warnings.push(format!(
+                    "Unknown status '{status_raw}' of {} - {} on {}, kept as unknown.",
+                    mch.team1, mch.team2, mch.date
+                ));
+                MatchStatus::Unknown
+            });
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ScoreRaw {
-    ht: Option<[u8; 2]>,
-    ft: Option<[u8; 2]>,
+    pub ht: Option<[u8; 2]>,
+    pub ft: Option<[u8; 2]>,
+    pub et: Option<[u8; 2]>,
+    pub p: Option<[u8; 2]>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Encode, Decode)]
+pub struct ScoreGoals(pub u8, pub u8);
+
+impl ScoreGoals {
+    pub fn total(&self) -> u8 {
+        self.0 + self.1
+    }
+
+    pub fn margin(&self) -> u8 {
+        self.0.abs_diff(self.1)
+    }
+
+    pub fn result(&self) -> MatchResult {
+        match self.0.cmp(&self.1) {
+            std::cmp::Ordering::Greater => MatchResult::Home,
+            std::cmp::Ordering::Equal => MatchResult::Draw,
+            std::cmp::Ordering::Less => MatchResult::Away,
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
-pub struct ScoreGoals(u8, u8);
+// Result from the point of view of team1, the home team
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchResult {
+    Home,
+    Draw,
+    Away,
+}
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+// How the result of a match was settled.
+// Extra time scores in openfootball data are cumulative (they include full time goals)
+// and penalty shootout scores only count the shootout itself.
+#[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq, Encode, Decode)]
+#[serde(rename_all(serialize = "snake_case"))]
+pub enum DecidedBy {
+    Regulation,
+    ExtraTime,
+    Penalties,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Encode, Decode)]
 #[serde(from = "ScoreRaw")]
 pub struct Score {
     pub half_time: Option<ScoreGoals>,
     pub full_time: Option<ScoreGoals>,
+    pub extra_time: Option<ScoreGoals>,
+    pub penalties: Option<ScoreGoals>,
+    pub decided_by: Option<DecidedBy>,
 }
 
 impl From<ScoreRaw> for Score {
     fn from(value: ScoreRaw) -> Self {
         let half_time = value.ht.map(|raw| ScoreGoals(raw[0], raw[1]));
         let full_time = value.ft.map(|raw| ScoreGoals(raw[0], raw[1]));
+        let extra_time = value.et.map(|raw| ScoreGoals(raw[0], raw[1]));
+        let penalties = value.p.map(|raw| ScoreGoals(raw[0], raw[1]));
+
+        let decided_by = if penalties.is_some() {
+            Some(DecidedBy::Penalties)
+        } else if extra_time.is_some() {
+            Some(DecidedBy::ExtraTime)
+        } else if full_time.is_some() {
+            Some(DecidedBy::Regulation)
+        } else {
+            None
+        };
 
         Score {
             half_time,
             full_time,
+            extra_time,
+            penalties,
+            decided_by,
         }
     }
 }
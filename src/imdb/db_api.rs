@@ -1,15 +1,22 @@
-use std::collections::BTreeMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use axum::http::StatusCode;
-use either::Either;
 
 use crate::{
     imdb::{
         IMDB, ReadyState,
         data_types::{
-            Match, MatchId, Season, SeasonId, Team, TeamId, TeamTournamentSeasonMatchMap,
-            TeamTournamentYearlyMatchMap, Tournament, TournamentId, Year,
+            FormMatch, FormResult, HeadToHead, HeadToHeadSide, LeaguePosition, Match, MatchDetail,
+            MatchId, MatchKey, MatchTeamContext, PhaseKey, PhaseMatchMap, PlayerGoals, PlayerId,
+            PlayerMatchGoals, Scorer, SearchHit, SearchKind, Season, SeasonId, Team, TeamAliases,
+            TeamId, TeamTournamentSeasonMatchMap, TeamTournamentYearlyMatchMap, Tournament,
+            TournamentId, Venue, VenueId,
         },
+        ingestion_report::IngestionReport,
+        match_query::MatchQuery,
+        round_info::{Matchday, Phase, RoundOf},
+        tournament_meta::{CompetitionKind, TournamentMeta},
     },
     rest_api::query_types::*,
 };
@@ -21,17 +28,19 @@ impl IMDB<ReadyState> {
         Vec::from_iter(self.season_map.values())
     }
 
-    pub fn tournaments(&self) -> Vec<Tournament<'_>> {
+    pub fn ingestion_report(&self) -> &IngestionReport {
+        &self.ingestion_report
+    }
+
+    pub fn tournaments(&self, filter: &TournamentQueryParams) -> Vec<Tournament<'_>> {
         let mut sort_it: Vec<_> = self
             .tournament_id_name_map
-            .iter()
-            .map(|tour| Tournament {
-                id: *tour.0,
-                name: tour.1,
-            })
+            .keys()
+            .filter_map(|tour_id| self.tournament_object(tour_id))
+            .filter(|tour| filter.is_empty() || tour.meta.is_some_and(|meta| filter.matches(meta)))
             .collect();
 
-        sort_it.sort_unstable_by(|first, second| first.id.cmp(&second.id));
+        sort_it.sort_unstable_by_key(|tour| tour.id);
 
         sort_it
     }
@@ -49,341 +58,596 @@ impl IMDB<ReadyState> {
         sort_it
     }
 
-    pub fn all_matches(&self) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        Ok((
-            self.season_match_map
-                .range(..)
-                .fold(0, |acc, list| acc + list.1.len()),
-            self.season_match_map
-                .range(..)
-                .flat_map(|(_, sea_matches)| self.matches_by_slice(sea_matches)),
-        ))
+    pub fn venues(&self) -> Vec<Venue<'_>> {
+        self.venue_id_name_map
+            .iter()
+            .map(|(venue_id, name)| {
+                let match_list = self
+                    .venue_match_map
+                    .get(venue_id)
+                    .map(|match_list| match_list.as_slice())
+                    .unwrap_or_default();
+
+                let home_team_ids = self
+                    .matches_by_slice(match_list)
+                    .filter_map(|mch| self.team_name_id_map.get(&mch.team1))
+                    .collect::<BTreeSet<_>>();
+
+                Venue {
+                    id: *venue_id,
+                    name,
+                    matches: match_list.len(),
+                    home_teams: home_team_ids
+                        .into_iter()
+                        .filter_map(|team_id| {
+                            self.team_id_name_map
+                                .get(team_id)
+                                .map(|name| Team { id: *team_id, name })
+                        })
+                        .collect(),
+                }
+            })
+            .collect()
     }
 
-    pub fn season_matches_by_id(
+    pub fn venue_matches_by_id(
         &self,
-        season_id: &SeasonId,
+        venue_id: &VenueId,
     ) -> Result<impl Iterator<Item = &Match>, StatusCode> {
-        self.season_match_map
-            .get(season_id)
+        self.venue_match_map
+            .get(venue_id)
             .map(|match_list| self.matches_by_slice(match_list))
             .ok_or(StatusCode::NOT_FOUND)
     }
 
-    pub fn match_by_id(&self, match_id: &MatchId) -> Result<&Match, StatusCode> {
-        self.match_data_map
-            .get(match_id)
-            .ok_or(StatusCode::NOT_FOUND)
-    }
-
-    pub fn tournament_by_id(&self, tour_id: &TournamentId) -> Result<&str, StatusCode> {
-        self.tournament_id_name_map
-            .get(tour_id)
-            .map(|tour| tour.as_str())
-            .ok_or(StatusCode::NOT_FOUND)
-    }
-
-    pub fn tournament_matches_by_id(
+    // Own goals don't count. Ties are broken by fewer penalties, then by name.
+    pub fn tournament_season_scorers(
         &self,
         tour_id: &TournamentId,
-    ) -> Result<impl Iterator<Item = &Match>, StatusCode> {
-        self.tournament_match_map
-            .get(tour_id)
-            .map(|match_list| self.matches_by_slice(match_list))
-            .ok_or(StatusCode::NOT_FOUND)
-    }
+        season_id: &SeasonId,
+        limit: usize,
+    ) -> Result<Vec<Scorer<'_>>, StatusCode> {
+        let season_map = self.get_inner_map(&self.tournament_season_match_map, tour_id)?;
+        let match_list = season_map.get(season_id).ok_or(StatusCode::NOT_FOUND)?;
+
+        let mut player_goals: HashMap<PlayerId, (usize, usize)> = HashMap::new();
+        self.matches_by_slice(match_list)
+            .flat_map(|mch| mch.goals.iter())
+            .filter(|goal| !goal.own_goal)
+            .for_each(|goal| {
+                let (goals, penalties) = player_goals.entry(goal.player_id).or_default();
+                *goals += 1;
+                *penalties += goal.penalty as usize;
+            });
+
+        let mut scorers = player_goals
+            .into_iter()
+            .filter_map(|(player_id, (goals, penalties))| {
+                let player = self.player_map.get(&player_id)?;
+                Some(Scorer {
+                    player,
+                    team: self.team_by_id(&player.team_id)?,
+                    goals,
+                    penalties,
+                })
+            })
+            .collect::<Vec<_>>();
 
-    pub fn yearly_matches_by_year(
-        &self,
-        year: &Year,
-    ) -> Result<impl Iterator<Item = &Match>, StatusCode> {
-        self.check_year_exists_in_range(year)?;
-        self.yearly_match_map
-            .get(year)
-            .map(|match_list| self.matches_by_slice(match_list))
-            .ok_or(StatusCode::NOT_FOUND)
+        scorers.sort_unstable_by(|a, b| {
+            b.goals
+                .cmp(&a.goals)
+                .then(a.penalties.cmp(&b.penalties))
+                .then(a.player.name.cmp(&b.player.name))
+        });
+        scorers.truncate(limit);
+
+        Ok(scorers)
     }
 
-    pub fn yearly_matches_year_range(
-        &self,
-        year_start: &Year,
-        year_end: &Year,
-    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        self.check_btreemap_range(year_start, year_end, &self.yearly_match_map)?;
+    // Goals of the player match by match, oldest first
+    pub fn player_goals(&self, player_id: &PlayerId) -> Result<PlayerGoals<'_>, StatusCode> {
+        let player = self
+            .player_map
+            .get(player_id)
+            .ok_or(StatusCode::NOT_FOUND)?;
+        let match_list = self.get_inner_map(&self.player_match_map, player_id)?;
+
+        let mut list = self
+            .matches_by_slice(match_list)
+            .map(|mch| PlayerMatchGoals {
+                mch,
+                goals: mch
+                    .goals
+                    .iter()
+                    .filter(|goal| goal.player_id == *player_id)
+                    .collect(),
+            })
+            .collect::<Vec<_>>();
+        list.sort_by_key(|match_goals| (match_goals.mch.date, match_goals.mch.id));
 
-        Ok((
-            self.yearly_match_map
-                .range(year_start..=year_end)
-                .fold(0, |acc, list| acc + list.1.len()),
-            self.yearly_match_map
-                .range(year_start..=year_end)
-                .flat_map(|(_, year_matches)| self.matches_by_slice(year_matches)),
-        ))
+        let own_goals = list
+            .iter()
+            .flat_map(|match_goals| match_goals.goals.iter())
+            .filter(|goal| goal.own_goal)
+            .count();
+        let goals = list
+            .iter()
+            .map(|match_goals| match_goals.goals.len())
+            .sum::<usize>()
+            - own_goals;
+
+        Ok(PlayerGoals {
+            player,
+            team: self
+                .team_by_id(&player.team_id)
+                .ok_or(StatusCode::NOT_FOUND)?,
+            goals,
+            own_goals,
+            list,
+        })
     }
 
-    pub fn tournament_matches_by_season_id(
+    // Meetings are the matches of the query, which has both teams set.
+    pub fn head_to_head(
         &self,
-        tour_id: &TournamentId,
-        season_id: &SeasonId,
-    ) -> Result<impl Iterator<Item = &Match>, StatusCode> {
-        let tour_map = self.get_inner_map(&self.tournament_season_match_map, tour_id)?;
-        let match_list = tour_map.get(season_id);
+        team_id: &TeamId,
+        other_id: &TeamId,
+        query: &MatchQuery,
+    ) -> Result<HeadToHead<'_>, StatusCode> {
+        if team_id == other_id {
+            return Err(StatusCode::BAD_REQUEST);
+        }
 
-        match match_list {
-            Some(list) => Ok(Either::Left(self.matches_by_slice(list))),
-            None => Ok(Either::Right([].iter())),
+        let (_, it) = self.query_matches(query)?;
+        let mut sides = [team_id, other_id].map(|team_id| {
+            self.team_by_id(team_id).map(|team| HeadToHeadSide {
+                team,
+                wins: 0,
+                draws: 0,
+                losses: 0,
+                goals_for: 0,
+                goals_against: 0,
+                biggest_win: None,
+            })
+        });
+        let [Some(_), Some(_)] = sides else {
+            return Err(StatusCode::NOT_FOUND);
+        };
+
+        let mut played = 0;
+        let mut first_meeting: Option<&Match> = None;
+        let mut last_meeting: Option<&Match> = None;
+
+        for mch in it {
+            let Some(full_time) = &mch.score.full_time else {
+                continue;
+            };
+            played += 1;
+
+            // Goals of the team and of the other team
+            let [team1_id, _] = self.match_team_ids(mch);
+            let goals = if team1_id == Some(*team_id) {
+                [full_time.0, full_time.1]
+            } else {
+                [full_time.1, full_time.0]
+            };
+
+            for (side_idx, side) in sides.iter_mut().flatten().enumerate() {
+                let (goals_for, goals_against) = (goals[side_idx], goals[1 - side_idx]);
+                side.goals_for += goals_for as usize;
+                side.goals_against += goals_against as usize;
+
+                match goals_for.cmp(&goals_against) {
+                    Ordering::Greater => {
+                        side.wins += 1;
+                        if side.biggest_win.is_none_or(|win| is_bigger_win(mch, win)) {
+                            side.biggest_win = Some(mch);
+                        }
+                    }
+                    Ordering::Equal => side.draws += 1,
+                    Ordering::Less => side.losses += 1,
+                }
+            }
+
+            if first_meeting.is_none_or(|first| (mch.date, mch.id) < (first.date, first.id)) {
+                first_meeting = Some(mch);
+            }
+            if last_meeting.is_none_or(|last| (mch.date, mch.id) > (last.date, last.id)) {
+                last_meeting = Some(mch);
+            }
         }
+
+        Ok(HeadToHead {
+            played,
+            sides: sides.map(Option::unwrap),
+            first_meeting,
+            last_meeting,
+        })
     }
 
-    pub fn tournament_matches_by_year(
+    pub fn search(
         &self,
-        tour_id: &TournamentId,
-        year: &Year,
-    ) -> Result<impl Iterator<Item = &Match>, StatusCode> {
-        self.check_year_exists_in_range(year)?;
-        let tour_year_map = self.get_inner_map(&self.tournament_yearly_match_map, tour_id)?;
-
-        let match_list = tour_year_map.get(year);
+        query: &str,
+        kind: Option<SearchKind>,
+        limit: usize,
+    ) -> Vec<SearchHit<'_>> {
+        self.search_index
+            .search(query, kind, limit)
+            .into_iter()
+            .filter_map(|found| {
+                let (name, country) = match found.kind {
+                    SearchKind::Team => (self.team_id_name_map.get(&found.id)?.as_str(), None),
+                    SearchKind::Tournament => {
+                        let tournament = self.tournament_object(&found.id)?;
+                        (tournament.name, tournament.country)
+                    }
+                };
+
+                Some(SearchHit {
+                    kind: found.kind,
+                    id: found.id,
+                    name,
+                    country,
+                    alias: found.alias,
+                })
+            })
+            .collect()
+    }
 
-        match match_list {
-            Some(list) => Ok(Either::Left(self.matches_by_slice(list))),
-            None => Ok(Either::Right([].iter())),
-        }
+    pub fn team_aliases_by_id(&self, team_id: &TeamId) -> Result<TeamAliases<'_>, StatusCode> {
+        let name = self
+            .team_id_name_map
+            .get(team_id)
+            .ok_or(StatusCode::NOT_FOUND)?;
+
+        Ok(TeamAliases {
+            id: *team_id,
+            name,
+            aliases: self
+                .team_id_alias_map
+                .get(team_id)
+                .map(|aliases| aliases.iter().map(|alias| alias.as_str()).collect())
+                .unwrap_or_default(),
+        })
     }
 
-    pub fn tournament_yearly_matches_year_range(
-        &self,
-        tour_id: &TournamentId,
-        year_start: &Year,
-        year_end: &Year,
-    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        self.check_btreemap_range(year_start, year_end, &self.yearly_match_map)?;
-        let tour_year_map = self.get_inner_map(&self.tournament_yearly_match_map, tour_id)?;
+    // Aliases resolve to the canonical team they were folded into.
+    pub fn team_aliases_by_name(&self, team_name: &str) -> Result<TeamAliases<'_>, StatusCode> {
+        let team_id = self
+            .team_name_id_map
+            .get(team_name)
+            .or_else(|| {
+                self.team_name_id_map
+                    .get(self.team_alias_resolver.resolve(team_name))
+            })
+            .ok_or(StatusCode::NOT_FOUND)?;
 
-        Ok((
-            tour_year_map
-                .range(year_start..=year_end)
-                .fold(0, |acc, list| acc + list.1.len()),
-            tour_year_map
-                .range(year_start..=year_end)
-                .flat_map(|(_, year_matches)| self.matches_by_slice(year_matches)),
-        ))
+        self.team_aliases_by_id(team_id)
     }
 
-    pub fn team_matches_by_id(
-        &self,
-        team_id: &TeamId,
-        home_away: &HomeAwayOption,
-    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        let team_map = self.get_team_home_away_map_season(home_away);
-        let tour_map = self.get_inner_map(team_map, team_id)?;
+    pub fn match_by_id(&self, match_id: &MatchId) -> Result<&Match, StatusCode> {
+        self.match_data_map
+            .get(match_id)
+            .ok_or(StatusCode::NOT_FOUND)
+    }
 
-        Ok((
-            tour_map.range(..).fold(0, |acc, sea_map| {
-                acc + sea_map.1.range(..).fold(0, |acc, item| acc + item.1.len())
-            }),
-            tour_map.range(..).flat_map(|(_, sea_map)| {
-                sea_map
-                    .range(..)
-                    .flat_map(|item| self.matches_by_slice(item.1))
-            }),
-        ))
+    pub fn match_key(&self, mch: &Match) -> MatchKey {
+        let tournament = self
+            .tournament_name_id_map
+            .iter()
+            .find(|(_, tour_id)| **tour_id == mch.tournament_id)
+            .map(|(tour_key, _)| tour_key.clone())
+            .unwrap_or_default();
+
+        MatchKey {
+            date: mch.date,
+            tournament,
+            team1: mch.team1.clone(),
+            team2: mch.team2.clone(),
+        }
     }
 
-    pub fn team_matches_by_season_id(
-        &self,
-        team_id: &TeamId,
-        season_id: &SeasonId,
-        home_away: &HomeAwayOption,
-    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        let team_map = self.get_team_home_away_map_season(home_away);
-        let tour_map = self.get_inner_map(team_map, team_id)?;
+    // None when the match isn't in this build of the database anymore
+    pub fn match_by_key(&self, key: &MatchKey) -> Option<&Match> {
+        let tour_id = self.tournament_name_id_map.get(&key.tournament)?;
 
-        Ok((
-            tour_map.range(..).fold(0, |acc, sea_map| {
-                acc + sea_map
-                    .1
-                    .get(season_id)
-                    .map(|match_list| acc + match_list.len())
-                    .unwrap_or(0)
-            }),
-            tour_map.range(..).flat_map(|(_, sea_map)| {
-                sea_map
-                    .get(season_id)
-                    .map(|match_list| Either::Left(self.matches_by_slice(match_list)))
-                    .unwrap_or(Either::Right([].iter()))
-            }),
-        ))
+        self.date_match_map
+            .get(&key.date)
+            .into_iter()
+            .flat_map(|match_list| self.matches_by_slice(match_list))
+            .find(|mch| {
+                mch.tournament_id == *tour_id && mch.team1 == key.team1 && mch.team2 == key.team2
+            })
     }
 
-    pub fn team_matches_by_year(
-        &self,
-        team_id: &TeamId,
-        year: &Year,
-        home_away: &HomeAwayOption,
-    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        self.check_year_exists_in_range(year)?;
+    pub fn match_detail(&self, match_id: &MatchId) -> Result<MatchDetail<'_>, StatusCode> {
+        const FORM_LENGTH: usize = 5;
 
-        let team_map = self.get_team_home_away_map_yearly(home_away);
-        let tour_map = self.get_inner_map(team_map, team_id)?;
+        let mch = self.match_by_id(match_id)?;
+        let [Some(team1_id), Some(team2_id)] = self.match_team_ids(mch) else {
+            return Err(StatusCode::NOT_FOUND);
+        };
 
-        Ok((
-            tour_map.range(..).fold(0, |acc, year_map| {
-                acc + year_map
-                    .1
-                    .get(year)
-                    .map(|match_list| acc + match_list.len())
-                    .unwrap_or(0)
-            }),
-            tour_map.range(..).flat_map(|(_, sea_map)| {
-                sea_map
-                    .get(year)
-                    .map(|match_list| Either::Left(self.matches_by_slice(match_list)))
-                    .unwrap_or(Either::Right([].iter()))
-            }),
-        ))
+        let is_league = self
+            .tournament_meta_map
+            .get(&mch.tournament_id)
+            .is_some_and(|meta| meta.kind == CompetitionKind::League);
+        let table = if is_league {
+            self.league_table_before(mch)
+        } else {
+            vec![]
+        };
+
+        let teams = [team1_id, team2_id].map(|team_id| {
+            self.team_by_id(&team_id).map(|team| MatchTeamContext {
+                team,
+                form: self.team_form_before(mch, &team_id, FORM_LENGTH),
+                position: table
+                    .iter()
+                    .position(|(id, row)| *id == team_id && row.played > 0)
+                    .map(|idx| {
+                        let (_, row) = &table[idx];
+                        LeaguePosition {
+                            position: idx + 1,
+                            played: row.played,
+                            points: row.points,
+                            goal_difference: row.goals_for as isize - row.goals_against as isize,
+                        }
+                    }),
+            })
+        });
+        let [Some(_), Some(_)] = teams else {
+            return Err(StatusCode::NOT_FOUND);
+        };
+
+        // Meetings in any tournament, in date order
+        let meetings_query = MatchQuery::new().team(team1_id).opponent(team2_id);
+        let (_, meetings) = self.query_matches(&meetings_query)?;
+        let match_key = (mch.date, mch.id);
+        let (mut previous_meeting, mut next_meeting): (Option<&Match>, Option<&Match>) =
+            (None, None);
+        for meeting in meetings {
+            let meeting_key = (meeting.date, meeting.id);
+            if meeting_key < match_key
+                && previous_meeting
+                    .is_none_or(|previous| meeting_key > (previous.date, previous.id))
+            {
+                previous_meeting = Some(meeting);
+            }
+            if meeting_key > match_key
+                && next_meeting.is_none_or(|next| meeting_key < (next.date, next.id))
+            {
+                next_meeting = Some(meeting);
+            }
+        }
+
+        Ok(MatchDetail {
+            mch,
+            tournament: self.tournament_by_id(&mch.tournament_id)?,
+            season: self
+                .season_map
+                .get(&mch.season_id)
+                .ok_or(StatusCode::NOT_FOUND)?,
+            teams: teams.map(Option::unwrap),
+            previous_meeting,
+            next_meeting,
+        })
+    }
+
+    // Names aren't unique without the country, like the 'Ligue 1' of Algeria and France,
+    // so tournaments are always shown with it.
+    pub fn tournament_by_id(&self, tour_id: &TournamentId) -> Result<Tournament<'_>, StatusCode> {
+        self.tournament_object(tour_id).ok_or(StatusCode::NOT_FOUND)
     }
 
-    pub fn team_matches_year_range(
+    pub fn phase_matches(
         &self,
-        team_id: &TeamId,
-        year_start: &Year,
-        year_end: &Year,
-        home_away: &HomeAwayOption,
+        phase: &Phase,
+        round_of: &Option<RoundOf>,
     ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        self.check_year_exists_in_range(year_start)?;
-        self.check_year_exists_in_range(year_end)?;
-
-        let team_map = self.get_team_home_away_map_yearly(home_away);
-        let tour_map = self.get_inner_map(team_map, team_id)?;
+        let (start, end) = Self::phase_key_range(phase, round_of);
 
         Ok((
-            tour_map.range(..).fold(0, |acc, (_, year_map)| {
-                acc + year_map
-                    .range(year_start..=year_end)
-                    .fold(0, |acc, (_, match_list)| acc + match_list.len())
-            }),
-            tour_map.range(..).flat_map(move |(_, year_map)| {
-                year_map
-                    .range(year_start..=year_end)
-                    .flat_map(|(_, match_list)| self.matches_by_slice(match_list))
-            }),
+            self.tournament_phase_match_map
+                .values()
+                .fold(0, |acc, phase_map| {
+                    acc + phase_map
+                        .range(start..=end)
+                        .fold(0, |acc, (_, match_list)| acc + match_list.len())
+                }),
+            self.tournament_phase_match_map
+                .values()
+                .flat_map(move |phase_map| {
+                    phase_map
+                        .range(start..=end)
+                        .flat_map(|(_, match_list)| self.matches_by_slice(match_list))
+                }),
         ))
     }
 
-    pub fn team_matches_by_tournament_id(
+    pub fn tournament_matches_by_phase(
         &self,
-        team_id: &TeamId,
         tour_id: &TournamentId,
-        home_away: &HomeAwayOption,
+        phase: &Phase,
+        round_of: &Option<RoundOf>,
     ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        let team_map = self.get_team_home_away_map_season(home_away);
-        let tour_map = self.get_inner_map(team_map, team_id)?;
-        let season_map = tour_map.get(tour_id);
-
-        match season_map {
-            Some(sea_map) => Ok((
-                sea_map.iter().fold(0, |acc, item| acc + item.1.len()),
-                Either::Left(
-                    sea_map
-                        .iter()
-                        .flat_map(|(_, match_list)| self.matches_by_slice(match_list)),
-                ),
-            )),
-            None => Ok((0, Either::Right([].iter()))),
-        }
+        let phase_map: &PhaseMatchMap =
+            self.get_inner_map(&self.tournament_phase_match_map, tour_id)?;
+        let (start, end) = Self::phase_key_range(phase, round_of);
+
+        Ok((
+            phase_map
+                .range(start..=end)
+                .fold(0, |acc, (_, match_list)| acc + match_list.len()),
+            phase_map
+                .range(start..=end)
+                .flat_map(|(_, match_list)| self.matches_by_slice(match_list)),
+        ))
     }
 
-    pub fn team_tournament_matches_by_season_id(
+    pub fn tournament_matches_by_matchday(
         &self,
-        team_id: &TeamId,
         tour_id: &TournamentId,
-        season_id: &SeasonId,
-        home_away: &HomeAwayOption,
+        matchday: &Matchday,
     ) -> Result<impl Iterator<Item = &Match>, StatusCode> {
-        let team_map = self.get_team_home_away_map_season(home_away);
-        let tour_map = self.get_inner_map(team_map, team_id)?;
-        let season_map = self.get_inner_map(tour_map, tour_id)?;
-        let match_list = season_map.get(season_id);
-
-        match match_list {
-            Some(list) => Ok(Either::Left(self.matches_by_slice(list))),
-            None => Ok(Either::Right([].iter())),
-        }
+        let matchday_map = self.get_inner_map(&self.tournament_matchday_match_map, tour_id)?;
+
+        matchday_map
+            .get(matchday)
+            .map(|match_list| self.matches_by_slice(match_list))
+            .ok_or(StatusCode::NOT_FOUND)
     }
 
-    pub fn team_tournament_matches_by_year(
+    pub fn tournament_matches_matchday_range(
         &self,
-        team_id: &TeamId,
         tour_id: &TournamentId,
-        year: &Year,
-        home_away: &HomeAwayOption,
-    ) -> Result<impl Iterator<Item = &Match>, StatusCode> {
-        self.check_year_exists_in_range(year)?;
-        let team_map = self.get_team_home_away_map_yearly(home_away);
+        matchday_start: &Matchday,
+        matchday_end: &Matchday,
+    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
+        let matchday_map = self.get_inner_map(&self.tournament_matchday_match_map, tour_id)?;
+        Self::check_btreemap_range(matchday_start, matchday_end, matchday_map)?;
 
-        team_map
-            .get(team_id)
-            .map(|tour_map| {
-                tour_map
+        Ok((
+            matchday_map
+                .range(matchday_start..=matchday_end)
+                .fold(0, |acc, (_, match_list)| acc + match_list.len()),
+            matchday_map
+                .range(matchday_start..=matchday_end)
+                .flat_map(|(_, match_list)| self.matches_by_slice(match_list)),
+        ))
+    }
+}
+
+// Utilities
+impl IMDB<ReadyState> {
+    fn tournament_object(&self, tour_id: &TournamentId) -> Option<Tournament<'_>> {
+        self.tournament_id_name_map
+            .get(tour_id)
+            .map(|name| Tournament {
+                id: *tour_id,
+                name,
+                // The country in the name, or else the one of the region in the file name
+                country: self
+                    .tournament_id_country_map
                     .get(tour_id)
-                    .map(|year_map| {
-                        year_map
-                            .get(year)
-                            .map(|match_list| Ok(Either::Left(self.matches_by_slice(match_list))))
-                            .unwrap_or(Ok(Either::Right([].iter())))
-                    })
-                    .unwrap_or(Ok(Either::Right([].iter())))
+                    .map(|country| country.as_str())
+                    .or_else(|| {
+                        self.tournament_meta_map
+                            .get(tour_id)
+                            .and_then(TournamentMeta::country_name)
+                    }),
+                meta: self.tournament_meta_map.get(tour_id),
             })
-            .unwrap_or(Ok(Either::Right([].iter())))
     }
 
-    pub fn team_tournament_matches_by_year_range(
-        &self,
-        team_id: &TeamId,
-        tour_id: &TournamentId,
-        year_start: &Year,
-        year_end: &Year,
-        home_away: &HomeAwayOption,
-    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
-        self.check_year_exists_in_range(year_start)?;
-        self.check_year_exists_in_range(year_end)?;
+    // Results of the team's latest matches with a score on days before the match
+    fn team_form_before(&self, mch: &Match, team_id: &TeamId, length: usize) -> Vec<FormMatch> {
+        let Some(day_before) = mch.date.pred_opt() else {
+            return vec![];
+        };
+        let query = MatchQuery::new().team(*team_id).date_to(day_before);
+        let Ok((_, it)) = self.query_matches(&query) else {
+            return vec![];
+        };
+
+        let mut played = it
+            .filter_map(|earlier| {
+                let full_time = earlier.score.full_time.as_ref()?;
+                let [team1_id, _] = self.match_team_ids(earlier);
+                let (goals_for, goals_against) = if team1_id == Some(*team_id) {
+                    (full_time.0, full_time.1)
+                } else {
+                    (full_time.1, full_time.0)
+                };
+
+                Some(FormMatch {
+                    match_id: earlier.id,
+                    date: earlier.date,
+                    result: match goals_for.cmp(&goals_against) {
+                        Ordering::Greater => FormResult::Win,
+                        Ordering::Equal => FormResult::Draw,
+                        Ordering::Less => FormResult::Loss,
+                    },
+                })
+            })
+            .collect::<Vec<_>>();
+        played.sort_unstable_by_key(|form_match| (form_match.date, form_match.match_id));
 
-        let team_map = self.get_team_home_away_map_yearly(home_away);
+        played.split_off(played.len().saturating_sub(length))
+    }
 
-        team_map
+    // Table of the match's tournament season from the scores of earlier days. Every team
+    // of the season is in it, those yet to play at the bottom. Ranked by points,
+    // goal difference, goals scored and name.
+    fn league_table_before(&self, mch: &Match) -> Vec<(TeamId, TableRow)> {
+        let match_list = self
+            .tournament_season_match_map
+            .get(&mch.tournament_id)
+            .and_then(|season_map| season_map.get(&mch.season_id))
+            .map(|match_list| match_list.as_slice())
+            .unwrap_or_default();
+
+        let mut rows: HashMap<TeamId, TableRow> = HashMap::new();
+        for season_match in self.matches_by_slice(match_list) {
+            let team_ids = self.match_team_ids(season_match);
+            for team_id in team_ids.iter().flatten() {
+                rows.entry(*team_id).or_default();
+            }
+
+            let ([Some(team1_id), Some(team2_id)], Some(full_time)) =
+                (team_ids, season_match.score.full_time.as_ref())
+            else {
+                continue;
+            };
+            if season_match.date >= mch.date {
+                continue;
+            }
+
+            for (team_id, goals_for, goals_against) in [
+                (team1_id, full_time.0, full_time.1),
+                (team2_id, full_time.1, full_time.0),
+            ] {
+                let row = rows.entry(team_id).or_default();
+                row.played += 1;
+                row.goals_for += goals_for as usize;
+                row.goals_against += goals_against as usize;
+                row.points += match goals_for.cmp(&goals_against) {
+                    Ordering::Greater => 3,
+                    Ordering::Equal => 1,
+                    Ordering::Less => 0,
+                };
+            }
+        }
+
+        let mut table = rows.into_iter().collect::<Vec<_>>();
+        table.sort_unstable_by_key(|(team_id, row)| {
+            (
+                row.played == 0,
+                Reverse(row.points),
+                Reverse(row.goals_for as isize - row.goals_against as isize),
+                Reverse(row.goals_for),
+                self.team_id_name_map.get(team_id),
+                *team_id,
+            )
+        });
+
+        table
+    }
+
+    fn team_by_id(&self, team_id: &TeamId) -> Option<Team<'_>> {
+        self.team_id_name_map
             .get(team_id)
-            .map(|tour_map| {
-                tour_map
-                    .get(tour_id)
-                    .map(|year_map| {
-                        Ok((
-                            year_map
-                                .range(year_start..=year_end)
-                                .fold(0, |acc, (_, match_list)| acc + match_list.len()),
-                            Either::Left(
-                                year_map
-                                    .range(year_start..=year_end)
-                                    .flat_map(|(_, match_list)| self.matches_by_slice(match_list)),
-                            ),
-                        ))
-                    })
-                    .unwrap_or(Ok((0, Either::Right([].iter()))))
-            })
-            .unwrap_or(Ok((0, Either::Right([].iter()))))
+            .map(|name| Team { id: *team_id, name })
     }
-}
 
-// Utilities
-impl IMDB<ReadyState> {
     fn matches_by_slice(&self, match_list: &[MatchId]) -> impl Iterator<Item = &Match> {
         match_list
             .iter()
             .map(|match_id| self.match_data_map.get(match_id).unwrap())
     }
 
+    // Both bounds are inclusive, a range of a single key is valid
     fn check_btreemap_range<K, V>(
-        &self,
         start: &K,
         end: &K,
         map: &BTreeMap<K, V>,
@@ -392,26 +656,17 @@ impl IMDB<ReadyState> {
         K: Ord,
     {
         let err = Err(StatusCode::NOT_FOUND);
-        if start == end || end < start {
+        if end < start {
             return err;
         }
 
         if !map.contains_key(start) && !map.contains_key(end) {
-            println!("Check btree range does not contain");
             return err;
         }
 
         Ok(())
     }
 
-    fn check_year_exists_in_range(&self, year: &Year) -> Result<(), StatusCode> {
-        if self.yearly_match_map.contains_key(year) {
-            Ok(())
-        } else {
-            Err(StatusCode::NOT_FOUND)
-        }
-    }
-
     fn get_inner_map<'a, K, V>(&self, map: &'a BTreeMap<K, V>, id: &K) -> Result<&'a V, StatusCode>
     where
         K: Ord,
@@ -419,7 +674,15 @@ impl IMDB<ReadyState> {
         map.get(id).ok_or(StatusCode::NOT_FOUND)
     }
 
-    fn get_team_home_away_map_season(
+    // Without a round_of all the rounds of the phase are in range
+    fn phase_key_range(phase: &Phase, round_of: &Option<RoundOf>) -> (PhaseKey, PhaseKey) {
+        match round_of {
+            Some(round_of) => ((*phase, Some(*round_of)), (*phase, Some(*round_of))),
+            None => ((*phase, None), (*phase, Some(RoundOf::MAX))),
+        }
+    }
+
+    pub(super) fn get_team_home_away_map_season(
         &self,
         home_away: &HomeAwayOption,
     ) -> &TeamTournamentSeasonMatchMap {
@@ -431,7 +694,7 @@ impl IMDB<ReadyState> {
         }
     }
 
-    fn get_team_home_away_map_yearly(
+    pub(super) fn get_team_home_away_map_yearly(
         &self,
         home_away: &HomeAwayOption,
     ) -> &TeamTournamentYearlyMatchMap {
@@ -443,3 +706,44 @@ impl IMDB<ReadyState> {
         }
     }
 }
+
+// Wider margin first, then more goals scored by the winner, then the earlier match
+fn is_bigger_win(mch: &Match, other: &Match) -> bool {
+    let win_key = |mch: &Match| {
+        mch.score.full_time.as_ref().map(|full_time| {
+            (
+                full_time.margin(),
+                full_time.0.max(full_time.1),
+                Reverse((mch.date, mch.id)),
+            )
+        })
+    };
+
+    win_key(mch) > win_key(other)
+}
+
+#[derive(Default)]
+struct TableRow {
+    played: usize,
+    points: usize,
+    goals_for: usize,
+    goals_against: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn btreemap_range_bounds_are_inclusive() {
+        let map = BTreeMap::from([(1, ()), (3, ()), (5, ())]);
+        let check = |start, end| IMDB::<ReadyState>::check_btreemap_range(&start, &end, &map);
+
+        assert_eq!(check(5, 5), Ok(()));
+        assert_eq!(check(1, 5), Ok(()));
+        assert_eq!(check(3, 9), Ok(()));
+        assert_eq!(check(5, 3), Err(StatusCode::NOT_FOUND));
+        assert_eq!(check(4, 4), Err(StatusCode::NOT_FOUND));
+        assert_eq!(check(6, 9), Err(StatusCode::NOT_FOUND));
+    }
+}
@@ -0,0 +1,108 @@
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use super::{IMDB, ReadyState};
+
+// Summary of what changed between two builds of the database.
+// Seasons, tournaments and teams are compared by name because ids are
+// handed out in build order and can differ between builds.
+#[derive(Debug, Default)]
+pub struct IMDBDiff {
+    pub matches_before: usize,
+    pub matches_after: usize,
+    pub seasons_added: Vec<String>,
+    pub seasons_removed: Vec<String>,
+    pub tournaments_added: Vec<String>,
+    pub tournaments_removed: Vec<String>,
+    pub teams_added: Vec<String>,
+    pub teams_removed: Vec<String>,
+}
+
+impl IMDB<ReadyState> {
+    pub fn diff(&self, newer: &Self) -> IMDBDiff {
+        let (seasons_added, seasons_removed) =
+            added_removed(self.season_labels(), newer.season_labels());
+        let (tournaments_added, tournaments_removed) = added_removed(
+            self.tournament_name_id_map.keys().collect(),
+            newer.tournament_name_id_map.keys().collect(),
+        );
+        let (teams_added, teams_removed) = added_removed(
+            self.team_id_name_map.values().collect(),
+            newer.team_id_name_map.values().collect(),
+        );
+
+        IMDBDiff {
+            matches_before: self.match_data_map.len(),
+            matches_after: newer.match_data_map.len(),
+            seasons_added,
+            seasons_removed,
+            tournaments_added,
+            tournaments_removed,
+            teams_added,
+            teams_removed,
+        }
+    }
+
+    fn season_labels(&self) -> BTreeSet<String> {
+        self.season_map
+            .values()
+            .map(|season| match season.end_year {
+                Some(end_year) => format!("{}-{end_year}", season.start_year),
+                None => season.start_year.to_string(),
+            })
+            .collect()
+    }
+}
+
+impl IMDBDiff {
+    pub fn is_empty(&self) -> bool {
+        self.matches_before == self.matches_after
+            && self.seasons_added.is_empty()
+            && self.seasons_removed.is_empty()
+            && self.tournaments_added.is_empty()
+            && self.tournaments_removed.is_empty()
+            && self.teams_added.is_empty()
+            && self.teams_removed.is_empty()
+    }
+}
+
+impl Display for IMDBDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "matches {} -> {}",
+            self.matches_before, self.matches_after
+        )?;
+
+        let lists = [
+            ("seasons added", &self.seasons_added),
+            ("seasons removed", &self.seasons_removed),
+            ("tournaments added", &self.tournaments_added),
+            ("tournaments removed", &self.tournaments_removed),
+            ("teams added", &self.teams_added),
+            ("teams removed", &self.teams_removed),
+        ];
+
+        for (label, list) in lists.into_iter().filter(|(_, list)| !list.is_empty()) {
+            write!(f, "; {label}: {}", list.join(", "))?;
+        }
+
+        Ok(())
+    }
+}
+
+fn added_removed<T: Ord + ToString>(
+    before: BTreeSet<T>,
+    after: BTreeSet<T>,
+) -> (Vec<String>, Vec<String>) {
+    (
+        after
+            .difference(&before)
+            .map(|item| item.to_string())
+            .collect(),
+        before
+            .difference(&after)
+            .map(|item| item.to_string())
+            .collect(),
+    )
+}
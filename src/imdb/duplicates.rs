@@ -0,0 +1,319 @@
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+use super::data_types::{Match, MatchList, SeasonId};
+use super::ingestion_report::IngestionReport;
+use super::team_aliases::TeamAliasResolver;
+use super::tournament_name::TournamentName;
+
+use crate::constants::{ERR_PFX, WARN_PFX};
+
+const MOD: &str = "DUPLICATES";
+
+// What to do when the same fixture is found more than once.
+// First and last are in folder and file name order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    #[default]
+    KeepFirst,
+    KeepLast,
+    // Leaves out every copy and records them as errors, so strict mode aborts
+    Reject,
+}
+
+impl FromStr for DuplicatePolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep_first" => Ok(Self::KeepFirst),
+            "keep_last" => Ok(Self::KeepLast),
+            "reject" => Ok(Self::Reject),
+            other => Err(format!(
+                "Unknown duplicate policy '{other}', should be one of: keep_first, keep_last, reject."
+            )),
+        }
+    }
+}
+
+// Parsed match lists of a season folder, waiting to be indexed
+pub struct ParsedFolder {
+    pub folder: String,
+    pub season_id: SeasonId,
+    pub match_lists: Vec<MatchList>,
+}
+
+// Folder, match list and match positions of a match in the parsed folders
+type MatchPosition = (usize, usize, usize);
+
+// A fixture is the same competition, date and teams after alias resolution
+type FixtureKey = (String, NaiveDate, String, String);
+
+// Finds fixtures that appear more than once across all parsed files and
+// leaves out copies according to the policy. Exact duplicates have the same
+// score and status, others are conflicts. Every left out copy is recorded to
+// the ingestion report of its file.
+pub fn resolve_duplicates(
+    parsed: &mut [ParsedFolder],
+    resolver: &TeamAliasResolver,
+    policy: DuplicatePolicy,
+    report: &mut IngestionReport,
+) {
+    const ERR_FN: &str = "::resolve_duplicates";
+
+    let mut fixtures: HashMap<FixtureKey, Vec<MatchPosition>> = HashMap::new();
+
+    for (folder_idx, parsed_folder) in parsed.iter().enumerate() {
+        for (list_idx, match_list) in parsed_folder.match_lists.iter().enumerate() {
+            let competition = TournamentName::parse(&match_list.name).key();
+
+            for (match_idx, mch) in match_list.matches.iter().enumerate() {
+                let key = (
+                    competition.clone(),
+                    mch.date,
+                    resolver.resolve(&mch.team1).to_string(),
+                    resolver.resolve(&mch.team2).to_string(),
+                );
+                fixtures
+                    .entry(key)
+                    .or_default()
+                    .push((folder_idx, list_idx, match_idx));
+            }
+        }
+    }
+
+    // Reported in the order the first copies were found in
+    let mut duplicates = fixtures
+        .into_values()
+        .filter(|positions| positions.len() > 1)
+        .collect::<Vec<_>>();
+    duplicates.sort_unstable();
+
+    let mut left_out: BTreeSet<MatchPosition> = BTreeSet::new();
+
+    for positions in duplicates.into_iter() {
+        let kept = match policy {
+            DuplicatePolicy::KeepFirst => positions.first().copied(),
+            DuplicatePolicy::KeepLast => positions.last().copied(),
+            DuplicatePolicy::Reject => None,
+        };
+
+        let first = match_at(parsed, positions[0]);
+        let is_exact = positions.iter().all(|position| {
+            let mch = match_at(parsed, *position);
+            mch.score == first.score && mch.status == first.status
+        });
+
+        let sources = positions
+            .iter()
+            .map(|position| source_at(parsed, *position))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        for position in positions.iter().filter(|position| Some(**position) != kept) {
+            let mch = match_at(parsed, *position);
+            let message = format!(
+                "{} of {} - {} on {} found in {sources}. {}",
+                if is_exact {
+                    "Exact duplicate"
+                } else {
+                    "Conflicting scores"
+                },
+                mch.team1,
+                mch.team2,
+                mch.date,
+                match kept {
+                    Some(kept) => format!("Kept the one in {}.", source_at(parsed, kept)),
+                    None => "All of them were rejected.".to_string(),
+                }
+            );
+
+            let (folder_idx, list_idx, _) = *position;
+            let folder = &parsed[folder_idx].folder;
+            let file_name = &parsed[folder_idx].match_lists[list_idx].file_name;
+            let file_report = report.folder(folder).file(file_name);
+            file_report.matches -= 1;
+
+            if policy == DuplicatePolicy::Reject {
+                eprintln!("{ERR_PFX} {MOD}{ERR_FN}: '{folder}/{file_name}': {message}");
+                file_report.error(message);
+            } else {
+                eprintln!("{WARN_PFX} {MOD}{ERR_FN}: '{folder}/{file_name}': {message}");
+                file_report.warning(message);
+            }
+
+            left_out.insert(*position);
+        }
+    }
+
+    for (folder_idx, parsed_folder) in parsed.iter_mut().enumerate() {
+        for (list_idx, match_list) in parsed_folder.match_lists.iter_mut().enumerate() {
+            let mut match_idx = 0;
+            match_list.matches.retain(|_| {
+                let keep = !left_out.contains(&(folder_idx, list_idx, match_idx));
+                match_idx += 1;
+                keep
+            });
+        }
+
+        // Lists that only had left out matches would have nothing to index
+        parsed_folder
+            .match_lists
+            .retain(|match_list| !match_list.matches.is_empty());
+    }
+
+    if !left_out.is_empty() {
+        println!(
+            "{MOD}: {} duplicate matches left out with policy {policy:?}.",
+            left_out.len()
+        );
+    }
+}
+
+fn match_at(parsed: &[ParsedFolder], position: MatchPosition) -> &Match {
+    let (folder_idx, list_idx, match_idx) = position;
+    &parsed[folder_idx].match_lists[list_idx].matches[match_idx]
+}
+
+fn source_at(parsed: &[ParsedFolder], position: MatchPosition) -> String {
+    let (folder_idx, list_idx, _) = position;
+    format!(
+        "'{}/{}'",
+        parsed[folder_idx].folder, parsed[folder_idx].match_lists[list_idx].file_name
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imdb::data_types::ScoreGoals;
+
+    fn parsed_folder(folder: &str, file_name: &str, matches: &str) -> ParsedFolder {
+        let mut match_list: MatchList = serde_json::from_str(&format!(
+            r#"{{"name": "UEFA Champions League 2024/25", "matches": {matches}}}"#
+        ))
+        .unwrap();
+        match_list.file_name = file_name.to_string();
+
+        ParsedFolder {
+            folder: folder.to_string(),
+            season_id: 0,
+            match_lists: vec![match_list],
+        }
+    }
+
+    // The same final in two files, team names differing only by their country suffix
+    fn parsed_folders(report: &mut IngestionReport) -> Vec<ParsedFolder> {
+        let parsed = vec![
+            parsed_folder(
+                "2024-25",
+                "uefa.cl.json",
+                r#"[
+                    {"date": "2025-05-31", "team1": "Paris Saint-Germain (FRA)", "team2": "Inter (ITA)", "score": {"ft": [5, 0]}},
+                    {"date": "2025-05-07", "team1": "Paris Saint-Germain (FRA)", "team2": "Arsenal FC (ENG)", "score": {"ft": [2, 1]}}
+                ]"#,
+            ),
+            parsed_folder(
+                "2024-25",
+                "uefa.cl.txt",
+                r#"[{"date": "2025-05-31", "team1": "Paris Saint-Germain", "team2": "Inter", "score": {"ft": [5, 1]}}]"#,
+            ),
+        ];
+        for parsed_folder in parsed.iter() {
+            for match_list in parsed_folder.match_lists.iter() {
+                report
+                    .folder(&parsed_folder.folder)
+                    .file(&match_list.file_name)
+                    .matches = match_list.matches.len();
+            }
+        }
+        parsed
+    }
+
+    fn resolve(policy: DuplicatePolicy) -> (Vec<ParsedFolder>, IngestionReport) {
+        let mut report = IngestionReport::default();
+        let mut parsed = parsed_folders(&mut report);
+        resolve_duplicates(
+            &mut parsed,
+            &TeamAliasResolver::default(),
+            policy,
+            &mut report,
+        );
+        (parsed, report)
+    }
+
+    fn full_times(parsed: &[ParsedFolder]) -> Vec<(&str, Option<ScoreGoals>)> {
+        parsed
+            .iter()
+            .flat_map(|parsed_folder| parsed_folder.match_lists.iter())
+            .flat_map(|match_list| {
+                match_list
+                    .matches
+                    .iter()
+                    .map(|mch| (match_list.file_name.as_str(), mch.score.full_time.clone()))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn keeps_the_first_copy() {
+        let (parsed, mut report) = resolve(DuplicatePolicy::KeepFirst);
+
+        assert_eq!(
+            full_times(&parsed),
+            [
+                ("uefa.cl.json", Some(ScoreGoals(5, 0))),
+                ("uefa.cl.json", Some(ScoreGoals(2, 1))),
+            ]
+        );
+
+        let file_report = report.folder("2024-25").file("uefa.cl.txt");
+        assert_eq!(file_report.matches, 0);
+        assert!(file_report.errors.is_empty());
+        assert_eq!(file_report.warnings.len(), 1);
+        assert!(file_report.warnings[0].starts_with("Conflicting scores"));
+        assert!(file_report.warnings[0].ends_with("Kept the one in '2024-25/uefa.cl.json'."));
+    }
+
+    #[test]
+    fn keeps_the_last_copy() {
+        let (parsed, mut report) = resolve(DuplicatePolicy::KeepLast);
+
+        assert_eq!(
+            full_times(&parsed),
+            [
+                ("uefa.cl.json", Some(ScoreGoals(2, 1))),
+                ("uefa.cl.txt", Some(ScoreGoals(5, 1))),
+            ]
+        );
+        assert_eq!(report.folder("2024-25").file("uefa.cl.json").matches, 1);
+        assert_eq!(report.folder("2024-25").file("uefa.cl.txt").matches, 1);
+    }
+
+    #[test]
+    fn rejects_every_copy() {
+        let (parsed, mut report) = resolve(DuplicatePolicy::Reject);
+
+        // The list left without matches is dropped
+        assert_eq!(parsed[1].match_lists.len(), 0);
+        assert_eq!(
+            full_times(&parsed),
+            [("uefa.cl.json", Some(ScoreGoals(2, 1)))]
+        );
+
+        for file_name in ["uefa.cl.json", "uefa.cl.txt"] {
+            let file_report = report.folder("2024-25").file(file_name);
+            assert_eq!(file_report.errors.len(), 1);
+            assert!(file_report.errors[0].ends_with("All of them were rejected."));
+        }
+    }
+
+    #[test]
+    fn parses_policies() {
+        assert_eq!("keep_last".parse(), Ok(DuplicatePolicy::KeepLast));
+        assert_eq!("reject".parse(), Ok(DuplicatePolicy::Reject));
+        assert!("keep".parse::<DuplicatePolicy>().is_err());
+    }
+}
@@ -0,0 +1,658 @@
+use std::error::Error;
+use std::fmt::Display;
+
+use chrono::{Datelike, NaiveDate, NaiveTime};
+
+use super::data_types::{Goal, Match, MatchList, MatchStatus, Score, ScoreRaw, Year};
+
+// Markers in front of round lines like "» Matchday 1" or "▪ Round of 16"
+const ROUND_MARKERS: [char; 2] = ['»', '▪'];
+const ROUND_PREFIXES: [&str; 8] = [
+    "Matchday",
+    "Round",
+    "Group",
+    "Quarterfinals",
+    "Semifinals",
+    "Final",
+    "Playoffs",
+    "Qualifying",
+];
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+
+#[derive(Debug)]
+pub enum FootballTxtError {
+    NameNotFound,
+    NoMatchesFound,
+    MatchBeforeDate(usize),
+}
+impl Display for FootballTxtError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use FootballTxtError::*;
+        match self {
+            NameNotFound => write!(
+                f,
+                "Tournament name header line like '= English Premier League 2024/25' not found."
+            ),
+            NoMatchesFound => write!(f, "No match lines found."),
+            MatchBeforeDate(line_no) => {
+                write!(f, "Match line {line_no} comes before any date line.")
+            }
+        }
+    }
+}
+impl Error for FootballTxtError {}
+
+// Parses an openfootball Football.TXT league file into the same structure
+// the json files are deserialized into. Example:
+//
+// = English Premier League 2024/25
+//
+// » Matchday 1
+//   Fri Aug 16 2024
+//     20:00  Manchester United   1-0 (0-0)  Fulham
+//   Sat Aug 17
+//     12:30  Ipswich Town        0-2 (0-0)  Liverpool  @ Portman Road, Ipswich
+//              [-; Salah 60', 90+2' (pen.)]
+//
+// Dates without a year get the year of the previous date, or the season start year
+// for the first one, and roll over to the next year when the month wraps around.
+pub fn parse_match_list(contents: &str, season_start_year: Year) -> anyhow::Result<MatchList> {
+    let mut name = None;
+    let mut round = None;
+    let mut date: Option<NaiveDate> = None;
+    let mut matches = vec![];
+    let mut skipped_lines = 0;
+
+    for (index, raw_line) in contents.lines().enumerate() {
+        let line = match raw_line.split_once('#') {
+            Some((line, _comment)) => line.trim(),
+            None => raw_line.trim(),
+        };
+
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(header) = line.strip_prefix('=') {
+            // Only the first header is the name, deeper ones like '== Group A' are stages
+            if name.is_none() && !header.starts_with('=') {
+                name = Some(header.trim().to_string());
+            }
+            continue;
+        }
+
+        if let Some(round_name) = parse_round_line(line) {
+            round = Some(round_name);
+            continue;
+        }
+
+        if let Some(parsed_date) = parse_date_line(line, date, season_start_year) {
+            date = Some(parsed_date);
+            continue;
+        }
+
+        // Goal scorer lines belong to the match above them
+        if let Some(goals) = parse_goal_line(line)
+            && let Some(last) = matches.last_mut()
+        {
+            let Match { goals1, goals2, .. } = last;
+            let [team1_goals, team2_goals] = goals;
+            goals1.extend(team1_goals);
+            goals2.extend(team2_goals);
+            continue;
+        }
+
+        // Other annotations
+        if line.starts_with(['[', '(']) {
+            continue;
+        }
+
+        match parse_match_line(line) {
+            Some(parsed) => {
+                let Some(date) = date else {
+                    return Err(FootballTxtError::MatchBeforeDate(index + 1).into());
+                };
+
+                matches.push(Match {
+                    id: 0,
+                    season_id: 0,
+                    tournament_id: 0,
+                    round: round.clone(),
+                    date,
+                    time: parsed.time,
+                    kickoff: None,
+                    team1: parsed.team1,
+                    team2: parsed.team2,
+                    score: parsed.score,
+                    stage: None,
+                    venue: parsed.venue,
+                    venue_id: None,
+                    goals1: vec![],
+                    goals2: vec![],
+                    goals: vec![],
+                    status_raw: None,
+                    status: parsed.status,
+                    round_info: Default::default(),
+                });
+            }
+            None => skipped_lines += 1,
+        }
+    }
+
+    let name = name.ok_or(FootballTxtError::NameNotFound)?;
+    if matches.is_empty() {
+        return Err(FootballTxtError::NoMatchesFound.into());
+    }
+
+    let mut warnings = vec![];
+    if skipped_lines > 0 {
+        warnings.push(format!(
+            "{skipped_lines} lines could not be recognized and were skipped."
+        ));
+    }
+
+    Ok(MatchList {
+        file_name: String::new(),
+        warnings,
+        name,
+        matches,
+    })
+}
+
+fn parse_round_line(line: &str) -> Option<String> {
+    if let Some(round_name) = line.strip_prefix(ROUND_MARKERS) {
+        return Some(round_name.trim().to_string());
+    }
+
+    ROUND_PREFIXES
+        .iter()
+        .any(|prefix| line.starts_with(prefix))
+        .then(|| line.to_string())
+}
+
+// Date lines: "Fri Aug 16 2024", "Sat Aug 17", "[Fri Aug/16]" or "Aug 16"
+fn parse_date_line(
+    line: &str,
+    previous: Option<NaiveDate>,
+    season_start_year: Year,
+) -> Option<NaiveDate> {
+    let line = line.trim_start_matches('[').trim_end_matches(']');
+    let mut tokens = line
+        .split(|ch: char| ch.is_whitespace() || ch == '/')
+        .filter(|token| !token.is_empty())
+        .peekable();
+
+    if tokens
+        .peek()
+        .is_some_and(|token| WEEKDAYS.contains(&token_key(token).as_str()))
+    {
+        tokens.next();
+    }
+
+    let month_key = token_key(tokens.next()?);
+    let month = MONTHS.iter().position(|month| *month == month_key)? as u32 + 1;
+    let day = tokens
+        .next()?
+        .trim_end_matches(['.', ','])
+        .parse::<u32>()
+        .ok()?;
+
+    let year = match tokens.next() {
+        Some(year) => year.parse::<i32>().ok()?,
+        None => match previous {
+            // Month wrapping around like from December to January means a new year
+            Some(previous) if month < previous.month() => previous.year() + 1,
+            Some(previous) => previous.year(),
+            None => season_start_year as i32,
+        },
+    };
+
+    // Anything left means this wasn't a date line after all
+    if tokens.next().is_some() {
+        return None;
+    }
+
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+fn token_key(token: &str) -> String {
+    token.chars().take(3).collect::<String>().to_lowercase()
+}
+
+struct MatchLine {
+    time: Option<NaiveTime>,
+    team1: String,
+    team2: String,
+    score: Score,
+    venue: Option<String>,
+    status: MatchStatus,
+}
+
+// Match lines: "20:00  Arsenal  2-0 (1-0)  Wolverhampton Wanderers",
+// "Chelsea  4-3 pen. 1-1 a.e.t. (1-1, 0-0)  Liverpool" or "Arsenal v Chelsea"
+fn parse_match_line(line: &str) -> Option<MatchLine> {
+    let mut tokens = line.split_whitespace().collect::<Vec<_>>();
+
+    let time = tokens.first().and_then(|token| {
+        NaiveTime::parse_from_str(token, "%H:%M")
+            .or_else(|_| NaiveTime::parse_from_str(token, "%H.%M"))
+            .ok()
+    });
+    if time.is_some() {
+        tokens.remove(0);
+    }
+
+    let score_start = tokens
+        .iter()
+        .position(|token| parse_goals(token).is_some() || *token == "v" || *token == "vs")?;
+    if score_start == 0 {
+        return None;
+    }
+
+    let team1 = tokens[..score_start].join(" ");
+
+    let mut scores = vec![];
+    let mut bracketed = vec![];
+    let mut markers = vec![];
+    let mut cursor = score_start
+        + read_scores(
+            &tokens[score_start..],
+            &mut scores,
+            &mut bracketed,
+            &mut markers,
+        );
+
+    // With 'v' the score comes after both teams, like 'Arsenal v Chelsea 2-1 (1-0)'.
+    // Whatever follows the score is kept for the status and venue below.
+    let mut team2_tokens = if matches!(tokens.get(cursor), Some(&"v" | &"vs")) {
+        cursor += 1;
+        let team2_end = tokens[cursor..]
+            .iter()
+            .position(|token| parse_goals(token).is_some())
+            .map_or(tokens.len(), |position| cursor + position);
+        let score_end = team2_end
+            + read_scores(
+                &tokens[team2_end..],
+                &mut scores,
+                &mut bracketed,
+                &mut markers,
+            );
+
+        [&tokens[cursor..team2_end], &tokens[score_end..]].concat()
+    } else {
+        tokens[cursor..].to_vec()
+    };
+
+    // Trailing status annotation like '[cancelled]'
+    let mut status = MatchStatus::Regular;
+    if let Some(last) = team2_tokens.last()
+        && let Some(annotation) = last
+            .strip_prefix('[')
+            .and_then(|last| last.strip_suffix(']'))
+    {
+        status = match annotation.to_lowercase().as_str() {
+            "postponed" => MatchStatus::Postponed,
+            "cancelled" | "canceled" => MatchStatus::Cancelled,
+            "awarded" => MatchStatus::Awarded,
+            "abandoned" => MatchStatus::Abandoned,
+            _ => status,
+        };
+        team2_tokens.pop();
+    }
+
+    // Venue annotations start with '@', like '@ Wembley Stadium, London'
+    let mut venue = None;
+    if let Some(venue_start) = team2_tokens.iter().position(|token| *token == "@") {
+        let venue_name = team2_tokens[venue_start + 1..].join(" ");
+        if !venue_name.is_empty() {
+            venue = Some(venue_name);
+        }
+        team2_tokens.truncate(venue_start);
+    }
+
+    let team2 = team2_tokens.join(" ");
+    if team2.is_empty() {
+        return None;
+    }
+
+    Some(MatchLine {
+        time,
+        team1,
+        team2,
+        score: build_score(&scores, &bracketed, &markers),
+        venue,
+        status,
+    })
+}
+
+// Reads score tokens like '2-1', '(1-0)' and 'a.e.t.' from the start of the tokens.
+// Returns how many were read.
+fn read_scores<'a>(
+    tokens: &[&'a str],
+    scores: &mut Vec<[u8; 2]>,
+    bracketed: &mut Vec<[u8; 2]>,
+    markers: &mut Vec<(&'a str, usize)>,
+) -> usize {
+    let mut read = 0;
+
+    for token in tokens {
+        let is_bracketed = token.starts_with('(') || token.ends_with(')');
+        match parse_goals(token.trim_matches(['(', ')', ','])) {
+            Some(goals) if is_bracketed => bracketed.push(goals),
+            Some(goals) => scores.push(goals),
+            None if matches!(*token, "a.e.t." | "aet" | "pen." | "p") => {
+                markers.push((*token, scores.len()))
+            }
+            None => break,
+        }
+        read += 1;
+    }
+
+    read
+}
+
+// Scores in front of 'pen.' are the shootout, in front of 'a.e.t.' the extra time result.
+// Bracketed scores are full time and half time when there was extra time, else only half time.
+fn build_score(scores: &[[u8; 2]], bracketed: &[[u8; 2]], markers: &[(&str, usize)]) -> Score {
+    let marker_of = |score_index: usize| {
+        markers
+            .iter()
+            .find(|(_, scores_before)| *scores_before == score_index + 1)
+            .map(|(marker, _)| *marker)
+    };
+
+    let mut raw = ScoreRaw {
+        ht: None,
+        ft: None,
+        et: None,
+        p: None,
+    };
+
+    for (index, goals) in scores.iter().enumerate() {
+        match marker_of(index) {
+            Some("pen." | "p") => raw.p = Some(*goals),
+            Some(_) => raw.et = Some(*goals),
+            None if raw.ft.is_none() => raw.ft = Some(*goals),
+            None => {}
+        }
+    }
+
+    if raw.et.is_some() && raw.ft.is_none() {
+        raw.ft = bracketed.first().copied();
+        raw.ht = bracketed.get(1).copied();
+    } else {
+        raw.ht = bracketed.first().copied();
+    }
+
+    Score::from(raw)
+}
+
+// Goal scorer lines: "[Kane 23' (pen.) Saka 45+2'; Havertz 78' (o.g.)]" with
+// the goals of team1 before the semicolon and "-" for a team without goals.
+fn parse_goal_line(line: &str) -> Option<[Vec<Goal>; 2]> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (team1_side, team2_side) = inner.split_once(';').unwrap_or((inner, ""));
+
+    let goals = [parse_goal_side(team1_side)?, parse_goal_side(team2_side)?];
+    if goals.iter().all(Vec::is_empty) {
+        return None;
+    }
+
+    Some(goals)
+}
+
+fn parse_goal_side(side: &str) -> Option<Vec<Goal>> {
+    let mut goals: Vec<Goal> = vec![];
+    let mut name_tokens = vec![];
+    let mut player = None;
+
+    for token in side.split_whitespace() {
+        let token = token.trim_end_matches(',');
+
+        if let Some((minute, offset)) = parse_minute(token) {
+            if !name_tokens.is_empty() {
+                player = Some(name_tokens.join(" "));
+                name_tokens.clear();
+            }
+
+            // A minute without a name in front isn't a goal line after all
+            goals.push(Goal {
+                player_id: 0,
+                player: player.clone()?,
+                team: 0,
+                minute: Some(minute),
+                offset,
+                penalty: false,
+                own_goal: false,
+            });
+            continue;
+        }
+
+        match token {
+            "-" => {}
+            "(pen.)" | "(p)" => goals.last_mut()?.penalty = true,
+            "(o.g.)" | "(og)" => goals.last_mut()?.own_goal = true,
+            _ => name_tokens.push(token),
+        }
+    }
+
+    // Names without any minute after them
+    if !name_tokens.is_empty() {
+        return None;
+    }
+
+    Some(goals)
+}
+
+// Minutes like 23' or 45+2'
+fn parse_minute(token: &str) -> Option<(u8, Option<u8>)> {
+    let minute = token.strip_suffix('\'')?;
+
+    match minute.split_once('+') {
+        Some((minute, offset)) => Some((minute.parse().ok()?, Some(offset.parse().ok()?))),
+        None => Some((minute.parse().ok()?, None)),
+    }
+}
+
+fn parse_goals(token: &str) -> Option<[u8; 2]> {
+    let (first, second) = token.split_once('-')?;
+
+    Some([first.parse().ok()?, second.parse().ok()?])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imdb::data_types::{DecidedBy, ScoreGoals};
+
+    const LEAGUE_FILE: &str = "\
+= English Premier League 2024/25
+
+» Matchday 1
+  Fri Aug 16 2024
+    20:00  Manchester United   1-0 (0-0)  Fulham
+  Sat Aug 17
+    12:30  Ipswich Town        0-2 (0-0)  Liverpool  @ Portman Road, Ipswich
+             [-; Salah 60', 90+2' (pen.)]
+";
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn parses_the_documented_example() {
+        let list = parse_match_list(LEAGUE_FILE, 2024).unwrap();
+
+        assert_eq!(list.name, "English Premier League 2024/25");
+        assert!(list.warnings.is_empty());
+        assert_eq!(list.matches.len(), 2);
+
+        let first = &list.matches[0];
+        assert_eq!(first.round.as_deref(), Some("Matchday 1"));
+        assert_eq!(first.date, date(2024, 8, 16));
+        assert_eq!(first.time, NaiveTime::from_hms_opt(20, 0, 0));
+        assert_eq!(
+            (first.team1.as_str(), first.team2.as_str()),
+            ("Manchester United", "Fulham")
+        );
+        assert_eq!(first.score.full_time, Some(ScoreGoals(1, 0)));
+        assert_eq!(first.score.half_time, Some(ScoreGoals(0, 0)));
+        assert_eq!(first.venue, None);
+
+        let second = &list.matches[1];
+        assert_eq!(second.date, date(2024, 8, 17));
+        assert_eq!(second.team2, "Liverpool");
+        assert_eq!(second.venue.as_deref(), Some("Portman Road, Ipswich"));
+        assert!(second.goals1.is_empty());
+        assert_eq!(second.goals2.len(), 2);
+        assert_eq!(
+            second
+                .goals2
+                .iter()
+                .map(|goal| (goal.player.as_str(), goal.minute, goal.offset, goal.penalty))
+                .collect::<Vec<_>>(),
+            [
+                ("Salah", Some(60), None, false),
+                ("Salah", Some(90), Some(2), true)
+            ]
+        );
+    }
+
+    #[test]
+    fn dates_without_year_roll_over_to_the_next_year() {
+        let contents = "\
+= Scottish Premiership 2024/25
+Sat Dec 28
+  Celtic  2-0  Dundee
+[Thu Jan/2]
+  Rangers  1-0  Celtic
+";
+        let list = parse_match_list(contents, 2024).unwrap();
+
+        assert_eq!(list.matches[0].date, date(2024, 12, 28));
+        assert_eq!(list.matches[1].date, date(2025, 1, 2));
+    }
+
+    #[test]
+    fn recognizes_round_lines() {
+        assert_eq!(
+            parse_round_line("» Matchday 1").as_deref(),
+            Some("Matchday 1")
+        );
+        assert_eq!(
+            parse_round_line("▪ Round of 16").as_deref(),
+            Some("Round of 16")
+        );
+        assert_eq!(
+            parse_round_line("Quarterfinals").as_deref(),
+            Some("Quarterfinals")
+        );
+        assert_eq!(parse_round_line("Arsenal  2-0  Chelsea"), None);
+    }
+
+    #[test]
+    fn parses_extra_time_and_penalty_scores() {
+        let parsed =
+            parse_match_line("Chelsea  4-3 pen. 1-1 a.e.t. (1-1, 0-0)  Liverpool").unwrap();
+
+        assert_eq!(
+            (parsed.team1.as_str(), parsed.team2.as_str()),
+            ("Chelsea", "Liverpool")
+        );
+        assert_eq!(parsed.score.penalties, Some(ScoreGoals(4, 3)));
+        assert_eq!(parsed.score.extra_time, Some(ScoreGoals(1, 1)));
+        assert_eq!(parsed.score.full_time, Some(ScoreGoals(1, 1)));
+        assert_eq!(parsed.score.half_time, Some(ScoreGoals(0, 0)));
+        assert_eq!(parsed.score.decided_by, Some(DecidedBy::Penalties));
+    }
+
+    #[test]
+    fn parses_fixtures_and_status_annotations() {
+        let parsed = parse_match_line("15.00  Arsenal v Chelsea [postponed]").unwrap();
+
+        assert_eq!(parsed.time, NaiveTime::from_hms_opt(15, 0, 0));
+        assert_eq!(
+            (parsed.team1.as_str(), parsed.team2.as_str()),
+            ("Arsenal", "Chelsea")
+        );
+        assert_eq!(parsed.score.full_time, None);
+        assert_eq!(parsed.score.decided_by, None);
+        assert_eq!(parsed.status, MatchStatus::Postponed);
+
+        assert!(parse_match_line("2-0  Chelsea").is_none());
+        assert!(parse_match_line("Arsenal 2-0").is_none());
+    }
+
+    #[test]
+    fn parses_goal_lines() {
+        let [team1_goals, team2_goals] =
+            parse_goal_line("[Kane 23' (pen.) Saka 45+2'; Havertz 78' (o.g.)]").unwrap();
+
+        assert_eq!(
+            team1_goals
+                .iter()
+                .map(|goal| (goal.player.as_str(), goal.minute, goal.offset, goal.penalty))
+                .collect::<Vec<_>>(),
+            [
+                ("Kane", Some(23), None, true),
+                ("Saka", Some(45), Some(2), false)
+            ]
+        );
+        assert_eq!(team2_goals.len(), 1);
+        assert_eq!(team2_goals[0].player, "Havertz");
+        assert!(team2_goals[0].own_goal);
+
+        // Annotations that aren't goals
+        assert!(parse_goal_line("[-; -]").is_none());
+        assert!(parse_goal_line("[Kane]").is_none());
+        assert!(parse_goal_line("[23']").is_none());
+        assert!(parse_goal_line("[cancelled]").is_none());
+    }
+
+    #[test]
+    fn scores_after_both_teams() {
+        let line = parse_match_line("20.00 Manchester United v Fulham FC 1-0 (0-0)").unwrap();
+        assert_eq!(line.time, NaiveTime::from_hms_opt(20, 0, 0));
+        assert_eq!(line.team1, "Manchester United");
+        assert_eq!(line.team2, "Fulham FC");
+        assert_eq!(line.score.full_time, Some(ScoreGoals(1, 0)));
+        assert_eq!(line.score.half_time, Some(ScoreGoals(0, 0)));
+
+        let line = parse_match_line("Arsenal v Chelsea 2-1 a.e.t. (1-1, 0-0) @ Wembley").unwrap();
+        assert_eq!(line.team2, "Chelsea");
+        assert_eq!(line.venue.as_deref(), Some("Wembley"));
+        assert_eq!(line.score.extra_time, Some(ScoreGoals(2, 1)));
+
+        // Fixtures without a score yet
+        let line = parse_match_line("Arsenal v Chelsea").unwrap();
+        assert_eq!(line.team2, "Chelsea");
+        assert!(line.score.full_time.is_none());
+    }
+
+    #[test]
+    fn match_lines_need_a_date_and_the_file_a_name() {
+        let err =
+            parse_match_list("= Premier League\n  Arsenal  2-0  Chelsea\n", 2024).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FootballTxtError>(),
+            Some(FootballTxtError::MatchBeforeDate(2))
+        ));
+
+        let err = parse_match_list("Sat Aug 17 2024\n  Arsenal  2-0  Chelsea\n", 2024).unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<FootballTxtError>(),
+            Some(FootballTxtError::NameNotFound)
+        ));
+
+        let list = parse_match_list(
+            "= Premier League\nSat Aug 17 2024\n  Arsenal  2-0  Chelsea\n  not a match\n",
+            2024,
+        )
+        .unwrap();
+        assert_eq!(list.warnings.len(), 1);
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+use bincode::{Decode, Encode};
+use serde::Serialize;
+
+use super::data_types::SeasonId;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Encode, Decode, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IngestionStatus {
+    #[default]
+    Ok,
+    Warnings,
+    Failed,
+}
+
+#[derive(Debug, Default, Serialize, Encode, Decode)]
+pub struct FileReport {
+    pub status: IngestionStatus,
+    pub matches: usize,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize, Encode, Decode)]
+pub struct FolderReport {
+    pub status: IngestionStatus,
+    pub season_id: Option<SeasonId>,
+    pub files_loaded: usize,
+    pub files_failed: usize,
+    pub matches: usize,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+    pub files: BTreeMap<String, FileReport>,
+}
+
+// What happened to every data root, season folder and file while the database
+// was built. Errors mean something was left out, warnings mean a file was loaded
+// but might not look like expected. Ones not tied to a folder, like those of
+// config files, are top level.
+#[derive(Debug, Default, Serialize, Encode, Decode)]
+pub struct IngestionReport {
+    pub status: IngestionStatus,
+    pub loaded_from_snapshot: bool,
+    pub folders_loaded: usize,
+    pub folders_failed: usize,
+    pub files_loaded: usize,
+    pub files_failed: usize,
+    pub matches: usize,
+    pub warnings: Vec<String>,
+    pub errors: Vec<String>,
+    pub folders: BTreeMap<String, FolderReport>,
+}
+
+// Context of the error of a failed build, so the report of what got ingested
+// until then can still be written out.
+#[derive(Debug)]
+pub struct FailedIngestion(pub IngestionReport);
+
+impl Display for FailedIngestion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Building the database failed.")
+    }
+}
+
+impl IngestionReport {
+    pub fn warning(&mut self, message: String) {
+        self.warnings.push(message);
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    pub fn folder(&mut self, folder: &str) -> &mut FolderReport {
+        self.folders.entry(folder.to_string()).or_default()
+    }
+
+    // Every recorded error prefixed with the folder and file it belongs to
+    pub fn failures(&self) -> Vec<String> {
+        let mut failures = self.errors.clone();
+
+        for (folder_name, folder) in self.folders.iter() {
+            failures.extend(
+                folder
+                    .errors
+                    .iter()
+                    .map(|error| format!("{folder_name}: {error}")),
+            );
+
+            for (file_name, file) in folder.files.iter() {
+                failures.extend(
+                    file.errors
+                        .iter()
+                        .map(|error| format!("{folder_name}/{file_name}: {error}")),
+                );
+            }
+        }
+
+        failures
+    }
+
+    // Counts and statuses are derived once everything has been recorded.
+    pub fn finish(&mut self) {
+        self.folders.values_mut().for_each(FolderReport::finish);
+
+        let (loaded, failed): (Vec<_>, Vec<_>) = self
+            .folders
+            .values()
+            .partition(|folder| folder.status != IngestionStatus::Failed);
+
+        self.folders_loaded = loaded.len();
+        self.folders_failed = failed.len();
+        self.files_loaded = self
+            .folders
+            .values()
+            .map(|folder| folder.files_loaded)
+            .sum();
+        self.files_failed = self
+            .folders
+            .values()
+            .map(|folder| folder.files_failed)
+            .sum();
+        self.matches = loaded.iter().map(|folder| folder.matches).sum();
+
+        self.status = if self.matches == 0 {
+            IngestionStatus::Failed
+        } else if !self.errors.is_empty()
+            || !self.warnings.is_empty()
+            || self
+                .folders
+                .values()
+                .any(|folder| folder.status != IngestionStatus::Ok)
+        {
+            IngestionStatus::Warnings
+        } else {
+            IngestionStatus::Ok
+        };
+    }
+}
+
+impl FolderReport {
+    pub fn warning(&mut self, message: String) {
+        self.warnings.push(message);
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    pub fn file(&mut self, file_name: &str) -> &mut FileReport {
+        self.files.entry(file_name.to_string()).or_default()
+    }
+
+    fn finish(&mut self) {
+        self.files.values_mut().for_each(FileReport::finish);
+
+        self.files_failed = self
+            .files
+            .values()
+            .filter(|file| file.status == IngestionStatus::Failed)
+            .count();
+        self.files_loaded = self.files.len() - self.files_failed;
+        self.matches = self.files.values().map(|file| file.matches).sum();
+
+        self.status = if !self.errors.is_empty() || self.matches == 0 {
+            IngestionStatus::Failed
+        } else if !self.warnings.is_empty()
+            || self
+                .files
+                .values()
+                .any(|file| file.status != IngestionStatus::Ok)
+        {
+            IngestionStatus::Warnings
+        } else {
+            IngestionStatus::Ok
+        };
+    }
+}
+
+impl FileReport {
+    pub fn warning(&mut self, message: String) {
+        self.warnings.push(message);
+    }
+
+    pub fn error(&mut self, message: String) {
+        self.errors.push(message);
+    }
+
+    // Files with errors that still had matches loaded, like ones with
+    // rejected duplicates, are only partly left out.
+    fn finish(&mut self) {
+        self.status = if !self.errors.is_empty() && self.matches == 0 {
+            IngestionStatus::Failed
+        } else if !self.errors.is_empty() || !self.warnings.is_empty() {
+            IngestionStatus::Warnings
+        } else {
+            IngestionStatus::Ok
+        };
+    }
+}
@@ -1,14 +1,20 @@
 use std::time::Instant;
-use std::{collections::BTreeMap, error::Error, fmt::Display, path::Path};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
 use anyhow::Context;
-use count_digits::CountDigits;
+use serde::de::DeserializeOwned;
 use tokio::{
     fs::{File, read_dir},
     io::AsyncReadExt,
 };
 
-const DATADIR_NAME: &str = "matchdata";
+use super::ingestion_report::IngestionReport;
+
 use crate::constants::ERR_PFX;
 
 type JsonDirectoryName = String;
@@ -20,6 +26,19 @@ pub type JsonFilesContentsAllRaw = BTreeMap<JsonDirectoryName, JsonFileContentsR
 
 const MOD: &str = "JSON_FETCHER";
 
+// Source files of match data, in the order they're preferred when a folder of one
+// data root has more than one file of a name
+pub const DATA_FILE_EXTENSIONS: [&str; 3] = [".json", ".txt", ".csv"];
+
+// Name of a source file without its extension, like 'en.1' of 'en.1.json',
+// and the rank of the extension
+pub fn source_stem(file_name: &str) -> Option<(&str, usize)> {
+    DATA_FILE_EXTENSIONS
+        .iter()
+        .enumerate()
+        .find_map(|(rank, extension)| file_name.strip_suffix(extension).map(|stem| (stem, rank)))
+}
+
 // Custom errors that correspond to edge cases of json fetcher
 #[derive(Debug)]
 pub enum JsonFetcherError {
@@ -40,15 +59,108 @@ impl Display for JsonFetcherError {
 }
 impl Error for JsonFetcherError {}
 
-pub async fn fetch_json_raw_data() -> anyhow::Result<JsonFilesContentsAllRaw> {
+// Data roots are merged in the order they're given.
+// When the same season folder and file exists in more than one root,
+// the one from the root that comes later wins. This way supplementary data can be
+// layered on top of the openfootball dump. A file wins over the files of the same
+// name in another format too, like 'en.1.txt' over an 'en.1.json' of an earlier root.
+// Skipped roots, folders and files are recorded to the ingestion report.
+pub async fn fetch_json_raw_data(
+    data_roots: &[PathBuf],
+    report: &mut IngestionReport,
+) -> anyhow::Result<JsonFilesContentsAllRaw> {
     const ERR_FN: &str = "::fetch_json_raw_data";
-    let mut directory_count = 0;
-    let mut file_count = 0;
+    let mut datadir_found = false;
 
     println!("{MOD}: fetching json files.");
     let now = Instant::now();
 
-    let datadir = Path::new(DATADIR_NAME);
+    let mut raw_file_contents: JsonFilesContentsAllRaw = BTreeMap::new();
+
+    for data_root in data_roots {
+        let root_contents = match fetch_datadir(data_root, report).await {
+            Ok(root_contents) => root_contents,
+            Err(err) => {
+                // Other roots might be ok
+                let message = format!("Could not fetch data root '{}': {err}", data_root.display());
+                eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing without it...");
+                report.error(message);
+                continue;
+            }
+        };
+        datadir_found = true;
+
+        for (dir_name, files) in root_contents.into_iter() {
+            let merged = raw_file_contents.entry(dir_name.clone()).or_default();
+
+            // Nothing of this root is merged yet, so these are all from earlier roots
+            let root_stems = files
+                .keys()
+                .filter_map(|file_name| source_stem(file_name).map(|(stem, _)| stem.to_string()))
+                .collect::<BTreeSet<_>>();
+            let overridden = merged
+                .keys()
+                .filter(|file_name| !files.contains_key(*file_name))
+                .filter(|file_name| {
+                    source_stem(file_name).is_some_and(|(stem, _)| root_stems.contains(stem))
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            for file_name in overridden {
+                merged.remove(&file_name);
+                let message = format!(
+                    "Left out, a file of the same name from data root '{}' is loaded instead.",
+                    data_root.display()
+                );
+                println!("{MOD}: '{dir_name}/{file_name}': {message}");
+                report.folder(&dir_name).file(&file_name).warning(message);
+            }
+
+            for (file_name, file_contents) in files.into_iter() {
+                if merged.insert(file_name.clone(), file_contents).is_some() {
+                    let message = format!(
+                        "Overridden by the one from data root '{}'.",
+                        data_root.display()
+                    );
+                    println!("{MOD}: '{dir_name}/{file_name}': {message}");
+                    report.folder(&dir_name).file(&file_name).warning(message);
+                }
+            }
+        }
+    }
+
+    // If we have no data to return, something is wrong.
+    // Signalling the caller to stop progress at this point
+    // and spit an error is a good idea.
+    if !datadir_found {
+        return Err(JsonFetcherError::DatadirNotFound.into());
+    }
+    if raw_file_contents.is_empty() {
+        return Err(JsonFetcherError::NoFilesFound.into());
+    }
+
+    println!(
+        "{MOD}: json fetch ended with elapsed milliseconds: {}",
+        now.elapsed().as_millis()
+    );
+
+    println!(
+        "{MOD}: contents of {} json files from {} directories converted to strings.",
+        raw_file_contents
+            .values()
+            .fold(0, |acc, files| acc + files.len()),
+        raw_file_contents.len()
+    );
+
+    Ok(raw_file_contents)
+}
+
+async fn fetch_datadir(
+    datadir: &Path,
+    report: &mut IngestionReport,
+) -> anyhow::Result<JsonFilesContentsAllRaw> {
+    const ERR_FN: &str = "::fetch_datadir";
+
     let mut raw_file_contents: JsonFilesContentsAllRaw = BTreeMap::new();
 
     if !datadir.is_dir() {
@@ -56,7 +168,8 @@ pub async fn fetch_json_raw_data() -> anyhow::Result<JsonFilesContentsAllRaw> {
     } else {
         let mut dir_contents = read_dir(datadir).await.with_context(|| {
             format!(
-                "{ERR_PFX} {MOD}{ERR_FN}: Failed to fetch main data directory '{DATADIR_NAME}' contents."
+                "{ERR_PFX} {MOD}{ERR_FN}: Failed to fetch data directory '{}' contents.",
+                datadir.display()
             )
         })?;
 
@@ -77,76 +190,83 @@ pub async fn fetch_json_raw_data() -> anyhow::Result<JsonFilesContentsAllRaw> {
                 // We print a soft error to the console and carry-on ignoring this
                 // offending directory.
                 if !validate_subdir_name(&dir_name) {
-                    eprintln!(
-                        "{ERR_PFX} {MOD}{ERR_FN}: Could not validate sub directory name '{}'. Continuing without it... The name should be in the format 2017-18 or 2017.",
-                        dir_name
+                    let message = format!(
+                        "Could not validate sub directory name '{dir_name}'. The name should be in the format 2017-18 or 2017."
                     );
+                    eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing without it...");
+                    report.folder(&dir_name).error(message);
                     // Skip this directory
                     continue;
                 }
 
-                let subdir_files = read_dir(&dir_path).await;
-
-                // Ignore the error condition at this point
-                // We'll use what we have
-                if let Ok(mut subdir_files) = subdir_files {
-                    raw_file_contents.insert(dir_name.clone(), BTreeMap::new());
-
-                    // We again ignore the error condition to give other files a chance
-                    // If no files are found, then we can "throw" a custom error `NoFilesFound` back to the caller
-                    // That confition is checked later and separately.
-                    while let Ok(Some(json_file)) = subdir_files.next_entry().await {
-                        let file_name = json_file.file_name().into_string().unwrap();
-                        let map = raw_file_contents.get_mut(&dir_name).unwrap();
-                        let fcontents_result = fetch_file_contents(&json_file.path()).await;
-
-                        // If file contents couldn't be read,
-                        // We'll print a soft error log only.
-                        // Because other files might be ok.
-                        match fcontents_result {
-                            Ok(file_contents) => {
-                                map.insert(file_name.clone(), file_contents);
-                                file_count += 1;
-                            }
-                            Err(err) => {
-                                eprintln!(
-                                    "{ERR_PFX} {MOD}{ERR_FN}: Can't read contents of file {}: {}. Continuing without it...",
-                                    &file_name, err
-                                );
-                            }
-                        }
+                let mut subdir_files = match read_dir(&dir_path).await {
+                    Ok(subdir_files) => subdir_files,
+                    Err(err) => {
+                        let message = format!(
+                            "Could not read the folder in data root '{}': {err}",
+                            datadir.display()
+                        );
+                        eprintln!(
+                            "{ERR_PFX} {MOD}{ERR_FN}: '{dir_name}': {message} Continuing without it..."
+                        );
+                        report.folder(&dir_name).error(message);
+                        continue;
                     }
+                };
+                let mut map = BTreeMap::new();
 
-                    // If no files found in the subdir to be of use,
-                    // We delete the entry to keep data clean
-                    let fetched_contents = raw_file_contents.get_mut(&dir_name).unwrap();
-                    if fetched_contents.is_empty() {
-                        raw_file_contents.remove(&dir_name);
-                    } else {
-                        directory_count += 1;
+                // Files that can't be read are left out, other files might be ok.
+                // If no files are found at all, then we can "throw" a custom error `NoFilesFound`
+                // back to the caller. That condition is checked later and separately.
+                loop {
+                    let json_file = match subdir_files.next_entry().await {
+                        Ok(Some(json_file)) => json_file,
+                        Ok(None) => break,
+                        Err(err) => {
+                            let message = format!(
+                                "Could not list the rest of the folder in data root '{}': {err}",
+                                datadir.display()
+                            );
+                            eprintln!(
+                                "{ERR_PFX} {MOD}{ERR_FN}: '{dir_name}': {message} Continuing without them..."
+                            );
+                            report.folder(&dir_name).error(message);
+                            break;
+                        }
+                    };
+                    let file_name = json_file.file_name().into_string().unwrap();
+
+                    match fetch_file_contents(&json_file.path()).await {
+                        Ok(file_contents) => {
+                            map.insert(file_name, file_contents);
+                        }
+                        Err(err) => {
+                            let message = format!("Can't read contents of file: {err}");
+                            eprintln!(
+                                "{ERR_PFX} {MOD}{ERR_FN}: '{dir_name}/{file_name}': {message}. Continuing without it..."
+                            );
+                            report.folder(&dir_name).file(&file_name).error(message);
+                        }
                     }
                 }
+
+                // Folders without files to use are left out, other data roots might have them
+                if map.is_empty() {
+                    let message = format!(
+                        "No files could be read from the folder in data root '{}'.",
+                        datadir.display()
+                    );
+                    eprintln!(
+                        "{ERR_PFX} {MOD}{ERR_FN}: '{dir_name}': {message} Continuing without it..."
+                    );
+                    report.folder(&dir_name).warning(message);
+                } else {
+                    raw_file_contents.insert(dir_name, map);
+                }
             }
         }
     }
 
-    // If we have no data to return, something is wrong.
-    // Signalling the caller to stop progress at this point
-    // and spit an error is a good idea.
-    if raw_file_contents.is_empty() {
-        return Err(JsonFetcherError::NoFilesFound.into());
-    }
-
-    println!(
-        "{MOD}: json fetch ended with elapsed milliseconds: {}",
-        now.elapsed().as_millis()
-    );
-
-    println!(
-        "{MOD}: contents of {} json files from {} directories converted to strings.",
-        file_count, directory_count
-    );
-
     Ok(raw_file_contents)
 }
 
@@ -155,15 +275,15 @@ pub async fn fetch_json_raw_data() -> anyhow::Result<JsonFilesContentsAllRaw> {
 fn validate_subdir_name(subdir_name: &str) -> bool {
     let split = subdir_name.split("-");
 
+    // Token length is checked instead of the digit count of the number
+    // so that end years with a leading zero like in 1999-00 are accepted.
     split.enumerate().all(|(index, item)| {
-        let parsed = item.parse::<u16>();
-        parsed.is_ok_and(|num| {
-            (index == 0 && num.count_digits() == 4) || (index == 1 && num.count_digits() == 2)
-        })
+        item.parse::<u16>().is_ok()
+            && ((index == 0 && item.len() == 4) || (index == 1 && item.len() == 2))
     })
 }
 
-async fn fetch_file_contents(path: &Path) -> anyhow::Result<String> {
+pub async fn fetch_file_contents(path: &Path) -> anyhow::Result<String> {
     const ERR_FN: &str = "::fetch_file_contents";
 
     let mut file_handle = File::open(path).await.with_context(|| {
@@ -186,3 +306,40 @@ async fn fetch_file_contents(path: &Path) -> anyhow::Result<String> {
 
     Ok(contents)
 }
+
+// Config files are optional. One that can't be read or parsed leaves the defaults
+// in place, which is recorded to the ingestion report. A file that doesn't parse
+// is an error, because it was meant to change something.
+pub async fn load_optional_config<T: DeserializeOwned + Default>(
+    config_file: &Path,
+    description: &str,
+    report: &mut IngestionReport,
+) -> T {
+    const ERR_FN: &str = "::load_optional_config";
+
+    let contents = match fetch_file_contents(config_file).await {
+        Ok(contents) => contents,
+        Err(err) => {
+            let message = format!(
+                "Could not read {description} '{}': {err}.",
+                config_file.display()
+            );
+            eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing with defaults...");
+            report.warning(message);
+            return T::default();
+        }
+    };
+
+    match serde_json::from_str::<T>(&contents) {
+        Ok(config) => config,
+        Err(err) => {
+            let message = format!(
+                "Error while parsing {description} '{}': {err}",
+                config_file.display()
+            );
+            eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing with defaults...");
+            report.error(message);
+            T::default()
+        }
+    }
+}
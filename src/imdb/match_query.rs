@@ -0,0 +1,656 @@
+use std::ops::{Bound, Range};
+use std::sync::atomic::Ordering;
+
+use axum::http::StatusCode;
+use chrono::{Datelike, NaiveDate};
+use either::Either;
+
+use super::data_types::{Match, MatchId, MatchStatus, SeasonId, TeamId, TournamentId, Year};
+use super::{IMDB, ReadyState};
+
+use crate::rest_api::query_types::{HomeAwayOption, SortOrder};
+
+// Any combination of match criteria. Every criterion that's set has to hold.
+// Home and away are from the point of view of the team, or of the opponent
+// when there is no team.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MatchQuery {
+    team: Option<TeamId>,
+    opponent: Option<TeamId>,
+    tournament: Option<TournamentId>,
+    season: Option<SeasonId>,
+    years: Option<(Year, Year)>,
+    date_from: Option<NaiveDate>,
+    date_to: Option<NaiveDate>,
+    home_away: HomeAwayOption,
+    status: Option<MatchStatus>,
+}
+
+impl MatchQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn team(mut self, team_id: TeamId) -> Self {
+        self.team = Some(team_id);
+        self
+    }
+
+    pub fn opponent(mut self, team_id: TeamId) -> Self {
+        self.opponent = Some(team_id);
+        self
+    }
+
+    pub fn tournament(mut self, tour_id: TournamentId) -> Self {
+        self.tournament = Some(tour_id);
+        self
+    }
+
+    pub fn season(mut self, season_id: SeasonId) -> Self {
+        self.season = Some(season_id);
+        self
+    }
+
+    pub fn year(self, year: Year) -> Self {
+        self.year_range(year, year)
+    }
+
+    pub fn year_range(mut self, year_start: Year, year_end: Year) -> Self {
+        self.years = Some((year_start, year_end));
+        self
+    }
+
+    pub fn date_from(mut self, date: NaiveDate) -> Self {
+        self.date_from = Some(date);
+        self
+    }
+
+    pub fn date_to(mut self, date: NaiveDate) -> Self {
+        self.date_to = Some(date);
+        self
+    }
+
+    pub fn home_away(mut self, home_away: HomeAwayOption) -> Self {
+        self.home_away = home_away;
+        self
+    }
+
+    pub fn status(mut self, status: MatchStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    // Year range that covers the years and dates of the query
+    fn year_bounds(&self) -> Option<(Year, Year)> {
+        let date_years = match (self.date_from, self.date_to) {
+            (None, None) => None,
+            (from, to) => Some((
+                from.map(|date| date.year() as Year).unwrap_or(Year::MIN),
+                to.map(|date| date.year() as Year).unwrap_or(Year::MAX),
+            )),
+        };
+
+        match (self.years, date_years) {
+            (Some((start, end)), Some((from, to))) => Some((start.max(from), end.min(to))),
+            (years, date_years) => years.or(date_years),
+        }
+    }
+}
+
+// Match id lists of one index that together hold every match of a criterion
+type Candidates<'a> = Vec<&'a [MatchId]>;
+
+// Ids of the matches of a query in id order. Without criteria that's every id up to
+// the head, with gaps where matches were left out, else the ids that were found.
+enum QueryIds {
+    All(Range<MatchId>),
+    Found(Vec<MatchId>),
+}
+
+type MatchIdIter<'a> = Box<dyn DoubleEndedIterator<Item = MatchId> + 'a>;
+pub type MatchIter<'a> = Box<dyn DoubleEndedIterator<Item = &'a Match> + 'a>;
+
+// Orders the indexes keep matches in. Matches of the same date are in id order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexedOrder {
+    Id,
+    Date(SortOrder),
+}
+
+impl IMDB<ReadyState> {
+    // Matches are in the order they were added to the database.
+    pub fn query_matches(
+        &self,
+        query: &MatchQuery,
+    ) -> Result<(usize, impl Iterator<Item = &Match>), StatusCode> {
+        let (total, match_ids) = match self.query_match_ids(query)? {
+            QueryIds::All(match_ids) => (self.match_data_map.len(), Either::Left(match_ids)),
+            QueryIds::Found(match_ids) => (match_ids.len(), Either::Right(match_ids.into_iter())),
+        };
+
+        Ok((
+            total,
+            match_ids.filter_map(|match_id| self.match_data_map.get(&match_id)),
+        ))
+    }
+
+    // Matches of a query split at a match, for pages that go on from it. The match is
+    // found with a binary search of the ids or the date index instead of a pass over
+    // every match. Returns the total and the matches before and after it in the order,
+    // or None when the indexes don't keep the order for this query.
+    pub fn query_matches_split(
+        &self,
+        query: &MatchQuery,
+        order: IndexedOrder,
+        split_match: Option<&Match>,
+    ) -> Result<Option<(usize, MatchIter<'_>, MatchIter<'_>)>, StatusCode> {
+        let (total, before, after): (usize, MatchIdIter, MatchIdIter) =
+            match (self.query_match_ids(query)?, order) {
+                (QueryIds::All(match_ids), IndexedOrder::Id) => {
+                    let (before, after) = match split_match {
+                        Some(mch) => (match_ids.start..mch.id, mch.id + 1..match_ids.end),
+                        None => (match_ids.start..match_ids.start, match_ids),
+                    };
+                    (self.match_data_map.len(), Box::new(before), Box::new(after))
+                }
+                (QueryIds::Found(mut match_ids), IndexedOrder::Id) => {
+                    let total = match_ids.len();
+                    let (start, end) = match split_match {
+                        Some(mch) => (
+                            match_ids.partition_point(|match_id| *match_id < mch.id),
+                            match_ids.partition_point(|match_id| *match_id <= mch.id),
+                        ),
+                        None => (0, 0),
+                    };
+                    let after = match_ids.split_off(end);
+                    match_ids.truncate(start);
+                    (
+                        total,
+                        Box::new(match_ids.into_iter()),
+                        Box::new(after.into_iter()),
+                    )
+                }
+                (QueryIds::All(_), IndexedOrder::Date(sort_order)) => {
+                    let (before, after) = self.date_split(sort_order, split_match);
+                    (self.match_data_map.len(), before, after)
+                }
+                (QueryIds::Found(_), IndexedOrder::Date(_)) => return Ok(None),
+            };
+
+        let get_match = |match_id| self.match_data_map.get(&match_id);
+
+        Ok(Some((
+            total,
+            Box::new(before.filter_map(get_match)),
+            Box::new(after.filter_map(get_match)),
+        )))
+    }
+
+    // Every match by date, split at a match. Matches of a day are in id order.
+    fn date_split(
+        &self,
+        sort_order: SortOrder,
+        split_match: Option<&Match>,
+    ) -> (MatchIdIter<'_>, MatchIdIter<'_>) {
+        let day_ids = |(_, match_list): (&NaiveDate, &Vec<MatchId>)| {
+            let mut match_ids = match_list.clone();
+            match_ids.sort_unstable();
+            match_ids
+        };
+
+        let Some(mch) = split_match else {
+            let days: MatchIdIter = match sort_order {
+                SortOrder::Asc => Box::new(self.date_match_map.iter().flat_map(day_ids)),
+                SortOrder::Desc => Box::new(self.date_match_map.iter().rev().flat_map(day_ids)),
+            };
+            return (Box::new(std::iter::empty()), days);
+        };
+
+        let mut day_before = self
+            .date_match_map
+            .get_key_value(&mch.date)
+            .map(day_ids)
+            .unwrap_or_default();
+        let day_after =
+            day_before.split_off(day_before.partition_point(|match_id| *match_id <= mch.id));
+        day_before.retain(|match_id| *match_id != mch.id);
+
+        let earlier = self.date_match_map.range(..mch.date);
+        let later = self
+            .date_match_map
+            .range((Bound::Excluded(mch.date), Bound::Unbounded));
+
+        // Days are reversed for descending dates, matches of a day stay in id order
+        match sort_order {
+            SortOrder::Asc => (
+                Box::new(earlier.flat_map(day_ids).chain(day_before)),
+                Box::new(day_after.into_iter().chain(later.flat_map(day_ids))),
+            ),
+            SortOrder::Desc => (
+                Box::new(later.rev().flat_map(day_ids).chain(day_before)),
+                Box::new(day_after.into_iter().chain(earlier.rev().flat_map(day_ids))),
+            ),
+        }
+    }
+
+    // Every criterion narrows down the matches with the index that fits it best.
+    // The smallest of those candidate lists is then checked against all the criteria,
+    // so the result is the intersection of the indexes.
+    fn query_match_ids(&self, query: &MatchQuery) -> Result<QueryIds, StatusCode> {
+        self.check_query_exists(query)?;
+
+        let team_sides = [
+            query.team.map(|team_id| (team_id, query.home_away)),
+            // The opponent plays at the other end of the pitch
+            query.opponent.map(|team_id| {
+                let home_away = match (query.team, query.home_away) {
+                    (None, home_away) => home_away,
+                    (Some(_), HomeAwayOption::Home) => HomeAwayOption::Away,
+                    (Some(_), HomeAwayOption::Away) => HomeAwayOption::Home,
+                    (Some(_), HomeAwayOption::Both) => HomeAwayOption::Both,
+                };
+                (team_id, home_away)
+            }),
+        ];
+
+        let mut candidate_lists: Vec<Candidates> = team_sides
+            .into_iter()
+            .flatten()
+            .map(|(team_id, home_away)| self.team_candidates(query, &team_id, &home_away))
+            .collect();
+
+        if let Some(tour_id) = query.tournament {
+            candidate_lists.push(self.tournament_candidates(query, &tour_id));
+        }
+        if let Some(season_id) = query.season {
+            candidate_lists.push(
+                self.season_match_map
+                    .get(&season_id)
+                    .map(Vec::as_slice)
+                    .into_iter()
+                    .collect(),
+            );
+        }
+        if let Some((year_start, year_end)) = query.years {
+            candidate_lists.push(
+                self.yearly_match_map
+                    .range(year_start..=year_end.max(year_start))
+                    .map(|(_, match_list)| match_list.as_slice())
+                    .collect(),
+            );
+        }
+        if query.date_from.is_some() || query.date_to.is_some() {
+            let date_from = query.date_from.unwrap_or(NaiveDate::MIN);
+            let date_to = query.date_to.unwrap_or(NaiveDate::MAX);
+
+            candidate_lists.push(
+                self.date_match_map
+                    .range(date_from..=date_to.max(date_from))
+                    .map(|(_, match_list)| match_list.as_slice())
+                    .collect(),
+            );
+        }
+        if let Some(status) = query.status {
+            candidate_lists.push(
+                self.status_match_map
+                    .get(&status)
+                    .map(Vec::as_slice)
+                    .into_iter()
+                    .collect(),
+            );
+        }
+
+        let Some(candidates) = candidate_lists
+            .into_iter()
+            .min_by_key(|candidates| candidates.iter().map(|list| list.len()).sum::<usize>())
+        else {
+            // Without criteria every match is in. Ids count up in the order matches
+            // were added, so they're looked up one by one instead of collected and sorted.
+            return Ok(QueryIds::All(1..self.match_id_head.load(Ordering::Acquire)));
+        };
+
+        let mut match_ids = candidates
+            .into_iter()
+            .flatten()
+            .copied()
+            .filter(|match_id| self.query_matches_match(query, &self.match_data_map[match_id]))
+            .collect::<Vec<_>>();
+        match_ids.sort_unstable();
+
+        Ok(QueryIds::Found(match_ids))
+    }
+
+    // Unknown teams, tournaments and seasons, or years outside of the data are not found.
+    fn check_query_exists(&self, query: &MatchQuery) -> Result<(), StatusCode> {
+        let teams_exist = [query.team, query.opponent]
+            .into_iter()
+            .flatten()
+            .all(|team_id| self.team_id_name_map.contains_key(&team_id));
+        let tournament_exists = query
+            .tournament
+            .is_none_or(|tour_id| self.tournament_id_name_map.contains_key(&tour_id));
+        let season_exists = query
+            .season
+            .is_none_or(|season_id| self.season_map.contains_key(&season_id));
+        let years_exist = query.years.is_none_or(|(year_start, year_end)| {
+            year_start <= year_end
+                && self
+                    .yearly_match_map
+                    .range(year_start..=year_end)
+                    .next()
+                    .is_some()
+        });
+
+        if teams_exist && tournament_exists && season_exists && years_exist {
+            Ok(())
+        } else {
+            Err(StatusCode::NOT_FOUND)
+        }
+    }
+
+    fn team_candidates(
+        &self,
+        query: &MatchQuery,
+        team_id: &TeamId,
+        home_away: &HomeAwayOption,
+    ) -> Candidates<'_> {
+        let tournament_filter =
+            |tour_id: &&TournamentId| query.tournament.is_none_or(|id| id == **tour_id);
+
+        // Season lists are the narrower ones when there is a season, else years narrow it down
+        if query.season.is_some() || query.year_bounds().is_none() {
+            let Some(tour_map) = self.get_team_home_away_map_season(home_away).get(team_id) else {
+                return vec![];
+            };
+
+            tour_map
+                .iter()
+                .filter(|(tour_id, _)| tournament_filter(tour_id))
+                .flat_map(|(_, season_map)| {
+                    season_map
+                        .iter()
+                        .filter(|(season_id, _)| query.season.is_none_or(|id| id == **season_id))
+                        .map(|(_, match_list)| match_list.as_slice())
+                })
+                .collect()
+        } else {
+            let Some(tour_map) = self.get_team_home_away_map_yearly(home_away).get(team_id) else {
+                return vec![];
+            };
+            let (year_start, year_end) = query.year_bounds().unwrap_or((Year::MIN, Year::MAX));
+
+            tour_map
+                .iter()
+                .filter(|(tour_id, _)| tournament_filter(tour_id))
+                .flat_map(|(_, year_map)| {
+                    year_map
+                        .range(year_start..=year_end.max(year_start))
+                        .map(|(_, match_list)| match_list.as_slice())
+                })
+                .collect()
+        }
+    }
+
+    fn tournament_candidates(&self, query: &MatchQuery, tour_id: &TournamentId) -> Candidates<'_> {
+        if let Some(season_id) = query.season {
+            return self
+                .tournament_season_match_map
+                .get(tour_id)
+                .and_then(|season_map| season_map.get(&season_id))
+                .map(Vec::as_slice)
+                .into_iter()
+                .collect();
+        }
+
+        if let Some((year_start, year_end)) = query.year_bounds() {
+            return self
+                .tournament_yearly_match_map
+                .get(tour_id)
+                .into_iter()
+                .flat_map(|year_map| year_map.range(year_start..=year_end.max(year_start)))
+                .map(|(_, match_list)| match_list.as_slice())
+                .collect();
+        }
+
+        self.tournament_match_map
+            .get(tour_id)
+            .map(Vec::as_slice)
+            .into_iter()
+            .collect()
+    }
+
+    fn query_matches_match(&self, query: &MatchQuery, mch: &Match) -> bool {
+        let [team1_id, team2_id] = self.match_team_ids(mch);
+        let plays = |team_id: TeamId, home_away: HomeAwayOption| match home_away {
+            HomeAwayOption::Both => team1_id == Some(team_id) || team2_id == Some(team_id),
+            HomeAwayOption::Home => team1_id == Some(team_id),
+            HomeAwayOption::Away => team2_id == Some(team_id),
+        };
+
+        let teams_match = match (query.team, query.opponent) {
+            (None, None) => true,
+            (Some(team_id), None) | (None, Some(team_id)) => plays(team_id, query.home_away),
+            (Some(team_id), Some(opponent_id)) => match query.home_away {
+                HomeAwayOption::Both => {
+                    (team1_id == Some(team_id) && team2_id == Some(opponent_id))
+                        || (team1_id == Some(opponent_id) && team2_id == Some(team_id))
+                }
+                HomeAwayOption::Home => team1_id == Some(team_id) && team2_id == Some(opponent_id),
+                HomeAwayOption::Away => team1_id == Some(opponent_id) && team2_id == Some(team_id),
+            },
+        };
+
+        let year = mch.date.year() as Year;
+
+        teams_match
+            && query
+                .tournament
+                .is_none_or(|tour_id| mch.tournament_id == tour_id)
+            && query
+                .season
+                .is_none_or(|season_id| mch.season_id == season_id)
+            && query
+                .years
+                .is_none_or(|(year_start, year_end)| (year_start..=year_end).contains(&year))
+            && query.date_from.is_none_or(|date| mch.date >= date)
+            && query.date_to.is_none_or(|date| mch.date <= date)
+            && query.status.is_none_or(|status| mch.status == status)
+    }
+
+    // Team names of matches can be aliases, which resolve to the canonical team
+    pub(super) fn match_team_ids(&self, mch: &Match) -> [Option<TeamId>; 2] {
+        [&mch.team1, &mch.team2].map(|team_name| self.team_name_id_map.get(team_name).copied())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEAGUE_FILE: &str = r#"{"name": "English Premier League 2024/25", "matches": [
+        {"round": "Matchday 1", "date": "2024-08-17", "team1": "Arsenal FC", "team2": "Chelsea FC", "score": {"ft": [1, 0]}},
+        {"round": "Matchday 1", "date": "2024-08-17", "team1": "Fulham FC", "team2": "Everton FC", "score": {"ft": [2, 2]}},
+        {"round": "Matchday 2", "date": "2024-12-28", "team1": "Chelsea FC", "team2": "Fulham FC", "score": {"ft": [0, 1]}},
+        {"round": "Matchday 3", "date": "2025-01-04", "team1": "Everton FC", "team2": "Arsenal FC", "score": {}, "status": "postponed"},
+        {"round": "Matchday 4", "date": "2025-05-10", "team1": "Chelsea FC", "team2": "Arsenal FC", "score": {"ft": [3, 3]}}
+    ]}"#;
+    const CUP_FILE: &str = r#"{"name": "English FA Cup 2024/25", "matches": [
+        {"round": "Final", "date": "2025-05-17", "team1": "Arsenal FC", "team2": "Chelsea FC", "score": {"ft": [2, 0]}}
+    ]}"#;
+
+    fn test_db(root_name: &str) -> IMDB<ReadyState> {
+        IMDB::from_test_files(
+            root_name,
+            &[(
+                "2024-25",
+                &[("en.1.json", LEAGUE_FILE), ("en.cup.json", CUP_FILE)],
+            )],
+        )
+    }
+
+    fn team_id(db: &IMDB<ReadyState>, team_name: &str) -> TeamId {
+        db.team_name_id_map[team_name]
+    }
+
+    fn fixtures(db: &IMDB<ReadyState>, query: &MatchQuery) -> Vec<(String, String)> {
+        let (total, it) = db.query_matches(query).unwrap();
+        let fixtures = it
+            .map(|mch| (mch.team1.clone(), mch.team2.clone()))
+            .collect::<Vec<_>>();
+        assert_eq!(total, fixtures.len());
+        fixtures
+    }
+
+    fn fixture(team1: &str, team2: &str) -> (String, String) {
+        (team1.to_string(), team2.to_string())
+    }
+
+    #[test]
+    fn intersects_every_criterion() {
+        let db = test_db("match-query-intersection");
+        let arsenal = team_id(&db, "Arsenal FC");
+        let chelsea = team_id(&db, "Chelsea FC");
+        let league = db.tournament_name_id_map["English Premier League"];
+
+        let query = MatchQuery::new().team(arsenal).opponent(chelsea);
+        assert_eq!(
+            fixtures(&db, &query),
+            [
+                fixture("Arsenal FC", "Chelsea FC"),
+                fixture("Chelsea FC", "Arsenal FC"),
+                fixture("Arsenal FC", "Chelsea FC"),
+            ]
+        );
+
+        let query = query.tournament(league);
+        assert_eq!(
+            fixtures(&db, &query),
+            [
+                fixture("Arsenal FC", "Chelsea FC"),
+                fixture("Chelsea FC", "Arsenal FC")
+            ]
+        );
+
+        let query = query.home_away(HomeAwayOption::Away);
+        assert_eq!(fixtures(&db, &query), [fixture("Chelsea FC", "Arsenal FC")]);
+
+        let query = query.year(2024);
+        assert_eq!(fixtures(&db, &query), []);
+    }
+
+    #[test]
+    fn dates_years_and_statuses() {
+        let db = test_db("match-query-dates");
+        let arsenal = team_id(&db, "Arsenal FC");
+
+        let query = MatchQuery::new().year(2024);
+        assert_eq!(fixtures(&db, &query).len(), 3);
+
+        let query = MatchQuery::new()
+            .date_from(NaiveDate::from_ymd_opt(2024, 12, 28).unwrap())
+            .date_to(NaiveDate::from_ymd_opt(2025, 5, 10).unwrap());
+        assert_eq!(
+            fixtures(&db, &query),
+            [
+                fixture("Chelsea FC", "Fulham FC"),
+                fixture("Everton FC", "Arsenal FC"),
+                fixture("Chelsea FC", "Arsenal FC"),
+            ]
+        );
+
+        let query = MatchQuery::new()
+            .team(arsenal)
+            .status(MatchStatus::Postponed);
+        assert_eq!(fixtures(&db, &query), [fixture("Everton FC", "Arsenal FC")]);
+
+        // Home and away of an opponent without a team are from the opponent's side
+        let query = MatchQuery::new()
+            .opponent(arsenal)
+            .home_away(HomeAwayOption::Home)
+            .date_to(NaiveDate::from_ymd_opt(2025, 5, 10).unwrap());
+        assert_eq!(fixtures(&db, &query), [fixture("Arsenal FC", "Chelsea FC")]);
+    }
+
+    #[test]
+    fn without_criteria_every_match_is_in() {
+        let db = test_db("match-query-all");
+
+        assert!(matches!(
+            db.query_match_ids(&MatchQuery::new()),
+            Ok(QueryIds::All(match_ids)) if match_ids == (1..7)
+        ));
+        assert_eq!(fixtures(&db, &MatchQuery::new()).len(), 6);
+    }
+
+    #[test]
+    fn unknown_ids_are_not_found() {
+        let db = test_db("match-query-not-found");
+
+        for query in [
+            MatchQuery::new().team(999),
+            MatchQuery::new().tournament(999),
+            MatchQuery::new().season(999),
+            MatchQuery::new().year(1990),
+            MatchQuery::new().year_range(2025, 2024),
+        ] {
+            assert!(matches!(
+                db.query_matches(&query),
+                Err(StatusCode::NOT_FOUND)
+            ));
+        }
+    }
+
+    #[test]
+    fn splits_at_a_match_in_index_order() {
+        let db = test_db("match-query-split");
+        let arsenal = team_id(&db, "Arsenal FC");
+        let split_ids = |query: &MatchQuery, order, split_id: MatchId| {
+            let split_match = &db.match_data_map[&split_id];
+            let (total, before, after) = db
+                .query_matches_split(query, order, Some(split_match))
+                .unwrap()
+                .unwrap();
+            (
+                total,
+                before.map(|mch| mch.id).collect::<Vec<_>>(),
+                after.map(|mch| mch.id).collect::<Vec<_>>(),
+            )
+        };
+
+        // Ids are in file order, the cup final comes last
+        let all = MatchQuery::new();
+        assert_eq!(
+            split_ids(&all, IndexedOrder::Id, 3),
+            (6, vec![1, 2], vec![4, 5, 6])
+        );
+        assert_eq!(
+            split_ids(&MatchQuery::new().team(arsenal), IndexedOrder::Id, 4),
+            (4, vec![1], vec![5, 6])
+        );
+
+        // Matches of the same date stay in id order in both orders
+        assert_eq!(
+            split_ids(&all, IndexedOrder::Date(SortOrder::Asc), 1),
+            (6, vec![], vec![2, 3, 4, 5, 6])
+        );
+        assert_eq!(
+            split_ids(&all, IndexedOrder::Date(SortOrder::Desc), 2),
+            (6, vec![6, 5, 4, 3, 1], vec![])
+        );
+        assert_eq!(
+            split_ids(&all, IndexedOrder::Date(SortOrder::Desc), 1),
+            (6, vec![6, 5, 4, 3], vec![2])
+        );
+
+        // Query results aren't kept in date order
+        assert!(
+            db.query_matches_split(
+                &MatchQuery::new().team(arsenal),
+                IndexedOrder::Date(SortOrder::Asc),
+                None
+            )
+            .unwrap()
+            .is_none()
+        );
+    }
+}
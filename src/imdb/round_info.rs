@@ -0,0 +1,215 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use super::tournament_meta::CompetitionKind;
+
+pub type Matchday = u16;
+pub type RoundOf = u16;
+
+// Section names that mark the post-season part of a league or the knockout part
+// of a competition. Other sections like "League", "Apertura" or "Group" are regular ones.
+const PLAYOFF_SECTIONS: [&str; 9] = [
+    "playoff",
+    "playout",
+    "relegation",
+    "championship",
+    "aufstieg",
+    "europa",
+    "europe",
+    "ecl",
+    "finals",
+];
+
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    Serialize,
+    Deserialize,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Encode,
+    Decode,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    Qualifying,
+    // Regular league season, group stage or league phase of a competition
+    #[default]
+    League,
+    // Post-season rounds of leagues: championship, relegation or promotion playoffs
+    Playoffs,
+    Knockout,
+    Final,
+}
+
+// Structured form of the openfootball `round` and `stage` fields.
+// Examples: "League, Matchday 3", "Finals, Quarterfinals", "Qualifying, Round 2", "Group A"
+#[derive(Debug, Clone, Default, Serialize, PartialEq, Eq, Encode, Decode)]
+pub struct RoundInfo {
+    pub phase: Phase,
+    // Number of teams left in a knockout round: 8 for quarterfinals, 2 for a final
+    pub round_of: Option<RoundOf>,
+    pub round_number: Option<u16>,
+    pub matchday: Option<Matchday>,
+    pub group: Option<String>,
+}
+
+impl RoundInfo {
+    pub fn parse(round: Option<&str>, stage: Option<&str>, kind: Option<CompetitionKind>) -> Self {
+        let round = round.map(str::trim).unwrap_or_default();
+
+        let (section, main) = match round.split_once(", ") {
+            Some((section, main)) => (Some(section), main),
+            None => (stage, round),
+        };
+
+        let is_league = kind.is_none_or(|kind| kind == CompetitionKind::League);
+        let is_qualifying = section.is_some_and(|sec| sec.to_lowercase().contains("qualif"));
+        let is_regular_section = section.is_none_or(|sec| {
+            let sec = sec.to_lowercase();
+            !PLAYOFF_SECTIONS.iter().any(|playoff| sec.contains(playoff))
+        });
+
+        // Knockout rounds of leagues are always playoffs,
+        // knockout rounds of cups and continental competitions are the main event.
+        let is_playoff = is_league || !(is_regular_section || section == Some("Finals"));
+
+        let mut info = Self::default();
+
+        if let Some(matchday) = parse_numbered(main, "Matchday") {
+            info.matchday = Some(matchday);
+            info.phase = if is_regular_section {
+                Phase::League
+            } else {
+                Phase::Playoffs
+            };
+        } else if let Some(group) = main.strip_prefix("Group ") {
+            info.group = Some(group.to_string());
+            info.phase = Phase::League;
+        } else if let Some(round_of) = parse_round_of(main) {
+            info.round_of = Some(round_of);
+            info.phase = if is_playoff {
+                Phase::Playoffs
+            } else if round_of == 2 {
+                Phase::Final
+            } else {
+                Phase::Knockout
+            };
+        } else if let Some(round_number) =
+            parse_numbered(main, "Round").or_else(|| parse_numbered(main, "Play-in round"))
+        {
+            info.round_number = Some(round_number);
+            info.phase = if is_playoff {
+                Phase::Playoffs
+            } else {
+                Phase::Knockout
+            };
+        } else if is_league {
+            info.phase = if is_regular_section && main.is_empty() {
+                Phase::League
+            } else {
+                Phase::Playoffs
+            };
+        } else {
+            info.phase = Phase::Knockout;
+        }
+
+        if is_qualifying {
+            info.phase = Phase::Qualifying;
+        }
+
+        info
+    }
+}
+
+// Parses tokens like "Matchday 12" or "Round 2"
+fn parse_numbered(main: &str, prefix: &str) -> Option<u16> {
+    main.strip_prefix(prefix)?.trim().parse().ok()
+}
+
+fn parse_round_of(main: &str) -> Option<RoundOf> {
+    if let Some(round_of) = parse_numbered(main, "Round of") {
+        return Some(round_of);
+    }
+
+    match main.to_lowercase().replace(['-', ' '], "").as_str() {
+        "final" => Some(2),
+        "semifinal" | "semifinals" => Some(4),
+        "quarterfinal" | "quarterfinals" => Some(8),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(round: &str, kind: CompetitionKind) -> RoundInfo {
+        RoundInfo::parse(Some(round), None, Some(kind))
+    }
+
+    #[test]
+    fn league_rounds() {
+        let info = parse("Matchday 3", CompetitionKind::League);
+        assert_eq!(info.phase, Phase::League);
+        assert_eq!(info.matchday, Some(3));
+
+        let info = parse("Relegation, Matchday 2", CompetitionKind::League);
+        assert_eq!(info.phase, Phase::Playoffs);
+        assert_eq!(info.matchday, Some(2));
+
+        let info = parse("Championship round, Final", CompetitionKind::League);
+        assert_eq!(info.phase, Phase::Playoffs);
+        assert_eq!(info.round_of, Some(2));
+
+        let info = RoundInfo::parse(None, None, None);
+        assert_eq!(info, RoundInfo::default());
+    }
+
+    #[test]
+    fn knockout_rounds() {
+        let info = parse("Finals, Quarterfinals", CompetitionKind::Continental);
+        assert_eq!(info.phase, Phase::Knockout);
+        assert_eq!(info.round_of, Some(8));
+
+        let info = parse("Semi-finals", CompetitionKind::Cup);
+        assert_eq!(info.phase, Phase::Knockout);
+        assert_eq!(info.round_of, Some(4));
+
+        let info = parse("Round of 16", CompetitionKind::Continental);
+        assert_eq!(info.phase, Phase::Knockout);
+        assert_eq!(info.round_of, Some(16));
+
+        let info = parse("Final", CompetitionKind::Cup);
+        assert_eq!(info.phase, Phase::Final);
+        assert_eq!(info.round_of, Some(2));
+
+        let info = parse("Round 2", CompetitionKind::Cup);
+        assert_eq!(info.phase, Phase::Knockout);
+        assert_eq!(info.round_number, Some(2));
+    }
+
+    #[test]
+    fn groups_and_qualifying() {
+        let info = parse("Group A", CompetitionKind::Continental);
+        assert_eq!(info.phase, Phase::League);
+        assert_eq!(info.group.as_deref(), Some("A"));
+
+        let info = parse("Qualifying, Round 2", CompetitionKind::Continental);
+        assert_eq!(info.phase, Phase::Qualifying);
+        assert_eq!(info.round_number, Some(2));
+
+        // Without a section in the round, the stage field is the section
+        let info = RoundInfo::parse(
+            Some("Matchday 1"),
+            Some("Europa League playoffs"),
+            Some(CompetitionKind::League),
+        );
+        assert_eq!(info.phase, Phase::Playoffs);
+        assert_eq!(info.matchday, Some(1));
+    }
+}
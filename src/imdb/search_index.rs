@@ -0,0 +1,442 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use bincode::{Decode, Encode};
+use deunicode::deunicode;
+use strsim::damerau_levenshtein;
+
+use super::data_types::{
+    SearchKind, TeamIdAliasMap, TeamIdNameMap, TournamentIdCountryMap, TournamentIdNameMap,
+    TournamentMetaMap,
+};
+use super::tournament_meta::TournamentMeta;
+
+// Index position of a name
+type EntryIdx = usize;
+// Index position of a word
+type WordIdx = usize;
+
+// How well a word of the query matched a word of a name, better is higher
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum WordMatch {
+    Typo = 1,
+    Prefix = 2,
+    Exact = 3,
+}
+
+#[derive(Encode, Decode)]
+struct SearchEntry {
+    kind: SearchKind,
+    id: usize,
+    name: String,
+    folded: String,
+    is_alias: bool,
+}
+
+pub struct SearchMatch<'a> {
+    pub kind: SearchKind,
+    pub id: usize,
+    // Alias the query matched, when it wasn't the name itself
+    pub alias: Option<&'a str>,
+}
+
+// Team names, team aliases and tournament names folded to lowercase ascii,
+// so 'München', 'Munchen' and 'MUNCHEN' are all the same word. Tournaments are
+// found by their country too, but it isn't part of the name.
+#[derive(Default, Encode, Decode)]
+pub struct SearchIndex {
+    entries: Vec<SearchEntry>,
+    // Every word in order, with the names it's in
+    words: Vec<(String, Vec<EntryIdx>)>,
+    // Words by the pairs of letters in them, to narrow down the words a typo might be in
+    bigram_word_map: HashMap<[u8; 2], Vec<WordIdx>>,
+}
+
+impl SearchIndex {
+    pub fn build(
+        team_id_name_map: &TeamIdNameMap,
+        team_id_alias_map: &TeamIdAliasMap,
+        tournament_id_name_map: &TournamentIdNameMap,
+        tournament_id_country_map: &TournamentIdCountryMap,
+        tournament_meta_map: &TournamentMetaMap,
+    ) -> Self {
+        let mut me = Self::default();
+        let mut word_entry_map: BTreeMap<String, Vec<EntryIdx>> = BTreeMap::new();
+        let mut index_words = |entry_idx: EntryIdx, words: Vec<String>| {
+            for word in words {
+                let entry_list = word_entry_map.entry(word).or_default();
+                if entry_list.last() != Some(&entry_idx) {
+                    entry_list.push(entry_idx);
+                }
+            }
+        };
+
+        for (team_id, name) in team_id_name_map.iter() {
+            let (entry_idx, words) = me.add(SearchKind::Team, *team_id, name, false);
+            index_words(entry_idx, words);
+
+            for alias in team_id_alias_map.get(team_id).into_iter().flatten() {
+                let (entry_idx, words) = me.add(SearchKind::Team, *team_id, alias, true);
+                index_words(entry_idx, words);
+            }
+        }
+        for (tour_id, name) in tournament_id_name_map.iter() {
+            let (entry_idx, words) = me.add(SearchKind::Tournament, *tour_id, name, false);
+            index_words(entry_idx, words);
+
+            // The country in the name, or else the one of the region in the file name
+            let countries = [
+                tournament_id_country_map.get(tour_id).map(String::as_str),
+                tournament_meta_map
+                    .get(tour_id)
+                    .and_then(TournamentMeta::country_name),
+            ];
+            index_words(
+                entry_idx,
+                countries
+                    .into_iter()
+                    .flatten()
+                    .flat_map(fold_words)
+                    .collect(),
+            );
+        }
+
+        me.words = word_entry_map.into_iter().collect();
+        for (word_idx, (word, _)) in me.words.iter().enumerate() {
+            for bigram in bigrams(word) {
+                me.bigram_word_map.entry(bigram).or_default().push(word_idx);
+            }
+        }
+
+        me
+    }
+
+    // Returns the words of the name to index it by
+    fn add(
+        &mut self,
+        kind: SearchKind,
+        id: usize,
+        name: &str,
+        is_alias: bool,
+    ) -> (EntryIdx, Vec<String>) {
+        let entry_idx = self.entries.len();
+        let words = fold_words(name);
+
+        self.entries.push(SearchEntry {
+            kind,
+            id,
+            name: name.to_string(),
+            folded: words.join(" "),
+            is_alias,
+        });
+
+        (entry_idx, words)
+    }
+
+    // Every word of the query has to match a word of the name exactly, as its start
+    // or with a typo or two. Better matching words rank higher, then names that start
+    // like the query, then names over aliases and shorter names. Teams and tournaments
+    // are only listed once, with their best matching name.
+    pub fn search(
+        &self,
+        query: &str,
+        kind: Option<SearchKind>,
+        limit: usize,
+    ) -> Vec<SearchMatch<'_>> {
+        let query_words = fold_words(query);
+        if query_words.is_empty() {
+            return vec![];
+        }
+
+        let mut entry_scores: HashMap<EntryIdx, usize> = HashMap::new();
+
+        for (word_idx, query_word) in query_words.iter().enumerate() {
+            let mut word_scores: HashMap<EntryIdx, WordMatch> = HashMap::new();
+
+            for (word, entry_list) in self.candidate_words(query_word) {
+                let Some(word_match) = match_word(query_word, word) else {
+                    continue;
+                };
+
+                for entry_idx in entry_list.iter() {
+                    let best = word_scores.entry(*entry_idx).or_insert(word_match);
+                    *best = (*best).max(word_match);
+                }
+            }
+
+            // Names stay in only as long as they matched every word so far
+            if word_idx == 0 {
+                entry_scores = word_scores
+                    .into_iter()
+                    .map(|(entry_idx, word_match)| (entry_idx, word_match as usize))
+                    .collect();
+            } else {
+                entry_scores.retain(|entry_idx, score| match word_scores.get(entry_idx) {
+                    Some(word_match) => {
+                        *score += *word_match as usize;
+                        true
+                    }
+                    None => false,
+                });
+            }
+        }
+
+        let folded_query = query_words.join(" ");
+        let mut ranked = entry_scores
+            .into_iter()
+            .map(|(entry_idx, score)| (&self.entries[entry_idx], score))
+            .filter(|(entry, _)| kind.is_none_or(|kind| entry.kind == kind))
+            .collect::<Vec<_>>();
+        ranked.sort_unstable_by_key(|(entry, score)| {
+            (
+                Reverse(*score),
+                !entry.folded.starts_with(&folded_query),
+                entry.is_alias,
+                entry.folded.len(),
+                &entry.name,
+                entry.id,
+            )
+        });
+
+        let mut listed = HashSet::new();
+        ranked
+            .into_iter()
+            .filter(|(entry, _)| listed.insert((entry.kind, entry.id)))
+            .take(limit)
+            .map(|(entry, _)| SearchMatch {
+                kind: entry.kind,
+                id: entry.id,
+                alias: entry.is_alias.then_some(entry.name.as_str()),
+            })
+            .collect()
+    }
+
+    // Words too short for typos can only be the start of a name word,
+    // so a range of the sorted words is enough. Every typo changes at most three
+    // letter pairs of a word, so longer ones only need to be checked against the
+    // words with enough of the pairs of the query word and about its length.
+    fn candidate_words<'a>(
+        &'a self,
+        query_word: &'a str,
+    ) -> Box<dyn Iterator<Item = &'a (String, Vec<EntryIdx>)> + 'a> {
+        let allowed = allowed_typos(query_word);
+        if allowed == 0 {
+            let start = self
+                .words
+                .partition_point(|(word, _)| word.as_str() < query_word);
+            return Box::new(
+                self.words[start..]
+                    .iter()
+                    .take_while(move |(word, _)| word.starts_with(query_word)),
+            );
+        }
+
+        let min_len = query_word.len().saturating_sub(allowed);
+        let query_bigrams = bigrams(query_word);
+        let min_shared = query_bigrams.len().saturating_sub(3 * allowed);
+        if min_shared == 0 {
+            return Box::new(
+                self.words
+                    .iter()
+                    .filter(move |(word, _)| word.len() >= min_len),
+            );
+        }
+
+        let mut shared_counts: HashMap<WordIdx, usize> = HashMap::new();
+        for bigram in query_bigrams.iter() {
+            for word_idx in self.bigram_word_map.get(bigram).into_iter().flatten() {
+                *shared_counts.entry(*word_idx).or_default() += 1;
+            }
+        }
+
+        Box::new(
+            shared_counts
+                .into_iter()
+                .filter(move |(_, shared)| *shared >= min_shared)
+                .map(|(word_idx, _)| &self.words[word_idx])
+                .filter(move |(word, _)| word.len() >= min_len),
+        )
+    }
+}
+
+// Distinct pairs of neighbouring letters of a folded word
+fn bigrams(word: &str) -> HashSet<[u8; 2]> {
+    word.as_bytes()
+        .windows(2)
+        .map(|pair| [pair[0], pair[1]])
+        .collect()
+}
+
+fn fold_words(text: &str) -> Vec<String> {
+    deunicode(text)
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_string())
+        .collect()
+}
+
+fn allowed_typos(query_word: &str) -> usize {
+    match query_word.len() {
+        0..=4 => 0,
+        5..=8 => 1,
+        _ => 2,
+    }
+}
+
+// Typos are also allowed in the start of a longer word, the rest may not be typed yet.
+fn match_word(query_word: &str, word: &str) -> Option<WordMatch> {
+    if word == query_word {
+        return Some(WordMatch::Exact);
+    }
+    if word.starts_with(query_word) {
+        return Some(WordMatch::Prefix);
+    }
+
+    let allowed = allowed_typos(query_word);
+    if allowed == 0 {
+        return None;
+    }
+
+    let word_start = &word[..word.len().min(query_word.len())];
+    let distance =
+        damerau_levenshtein(query_word, word).min(damerau_levenshtein(query_word, word_start));
+
+    (distance <= allowed).then_some(WordMatch::Typo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    fn test_index() -> SearchIndex {
+        let team_id_name_map = TeamIdNameMap::from([
+            (1, "Bayern München".to_string()),
+            (2, "Manchester United".to_string()),
+            (3, "Manchester City".to_string()),
+            (4, "Borussia Mönchengladbach".to_string()),
+        ]);
+        let team_id_alias_map =
+            TeamIdAliasMap::from([(1, BTreeSet::from(["FC Bayern".to_string()]))]);
+        let tournament_id_name_map = TournamentIdNameMap::from([
+            (1, "Deutsche Bundesliga".to_string()),
+            (2, "Super League".to_string()),
+        ]);
+        let tournament_id_country_map = TournamentIdCountryMap::from([(1, "Germany".to_string())]);
+        let tournament_meta_map = TournamentMetaMap::from([
+            (1, TournamentMeta::from_file_name("de.1.json").unwrap()),
+            (2, TournamentMeta::from_file_name("cn.1.json").unwrap()),
+        ]);
+
+        SearchIndex::build(
+            &team_id_name_map,
+            &team_id_alias_map,
+            &tournament_id_name_map,
+            &tournament_id_country_map,
+            &tournament_meta_map,
+        )
+    }
+
+    fn search<'a>(
+        index: &'a SearchIndex,
+        query: &str,
+        kind: Option<SearchKind>,
+    ) -> Vec<(SearchKind, usize, Option<&'a str>)> {
+        index
+            .search(query, kind, 10)
+            .into_iter()
+            .map(|found| (found.kind, found.id, found.alias))
+            .collect()
+    }
+
+    #[test]
+    fn folds_accents_and_case() {
+        let index = test_index();
+
+        // 'Monchengladbach' starts like 'munchen' with a typo, so it comes second
+        for query in ["munchen", "MÜNCHEN"] {
+            assert_eq!(
+                search(&index, query, None),
+                [(SearchKind::Team, 1, None), (SearchKind::Team, 4, None)]
+            );
+        }
+        assert_eq!(
+            search(&index, "bayern mun", None),
+            [(SearchKind::Team, 1, None)]
+        );
+        assert_eq!(
+            search(&index, "fc bay", None),
+            [(SearchKind::Team, 1, Some("FC Bayern"))]
+        );
+        assert_eq!(search(&index, "  ", None), []);
+    }
+
+    #[test]
+    fn ranks_prefixes_and_typos() {
+        let index = test_index();
+
+        // Prefixes before typos, then shorter names first when they match as well
+        assert_eq!(
+            search(&index, "manch", None)[..2],
+            [(SearchKind::Team, 3, None), (SearchKind::Team, 2, None)]
+        );
+        // Every word has to match
+        assert_eq!(search(&index, "manchster utd", None), []);
+        assert_eq!(
+            search(&index, "manchster united", None),
+            [(SearchKind::Team, 2, None)]
+        );
+        assert_eq!(
+            search(&index, "bayren", None),
+            [(SearchKind::Team, 1, None)]
+        );
+        assert_eq!(
+            search(&index, "monchengladbahc", None),
+            [(SearchKind::Team, 4, None)]
+        );
+
+        // Words of up to four letters can't have typos
+        assert_eq!(search(&index, "cty", None), []);
+    }
+
+    #[test]
+    fn finds_tournaments_by_country() {
+        let index = test_index();
+
+        assert_eq!(
+            search(&index, "germany", None),
+            [(SearchKind::Tournament, 1, None)]
+        );
+        // The country of the region in the file name, when the name has none
+        assert_eq!(
+            search(&index, "china super", None),
+            [(SearchKind::Tournament, 2, None)]
+        );
+        assert_eq!(search(&index, "bundesliga", Some(SearchKind::Team)), []);
+        assert_eq!(index.search("manchester", None, 1).len(), 1);
+    }
+
+    #[test]
+    fn typo_candidates_share_letter_pairs() {
+        let index = test_index();
+        let candidates = |query_word: &str| {
+            let mut words = index
+                .candidate_words(query_word)
+                .map(|(word, _)| word.clone())
+                .collect::<Vec<_>>();
+            words.sort_unstable();
+            words
+        };
+
+        assert_eq!(
+            bigrams("bayern"),
+            HashSet::from([*b"ba", *b"ay", *b"ye", *b"er", *b"rn"])
+        );
+        assert_eq!(candidates("monchengladbahc"), ["monchengladbach"]);
+        assert!(candidates("bayren").contains(&"bayern".to_string()));
+        assert!(!candidates("bayren").contains(&"manchester".to_string()));
+        // Without a typo only words that start like the query are candidates
+        assert_eq!(candidates("man"), ["manchester"]);
+    }
+}
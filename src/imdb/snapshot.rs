@@ -0,0 +1,234 @@
+use std::error::Error;
+use std::fmt::Display;
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::Context;
+use bincode::{Decode, Encode};
+use xxhash_rust::xxh3::Xxh3;
+
+use super::json_fetcher::{JsonFilesContentsAllRaw, fetch_file_contents};
+use super::{IMDB, ReadyState};
+
+use crate::constants::ERR_PFX;
+
+const MOD: &str = "SNAPSHOT";
+
+// Bump this whenever a change to the database structure or any of its
+// data types changes the binary layout, or the checksum is computed differently.
+// Old snapshots are then rebuilt.
+const SNAPSHOT_VERSION: u32 = 12;
+
+#[derive(Debug)]
+pub enum SnapshotError {
+    VersionMismatch(u32),
+    SourcesChanged,
+}
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use SnapshotError::*;
+        match self {
+            VersionMismatch(version) => write!(
+                f,
+                "Snapshot version {version} doesn't match the current version {SNAPSHOT_VERSION}."
+            ),
+            SourcesChanged => write!(f, "Source files changed since the snapshot was written."),
+        }
+    }
+}
+impl Error for SnapshotError {}
+
+#[derive(Encode, Decode)]
+struct SnapshotHeader {
+    version: u32,
+    checksum: u64,
+}
+
+// Checksum over everything the database is built from: names and contents of the
+// data files, the contents of the config files that change how they're indexed
+// and settings that do the same. Every field is prefixed with its length and every
+// list with its count, so that bytes moving from one field to the next, like a
+// file name ending where its contents started, change the checksum.
+pub async fn source_checksum(
+    raw_data: &JsonFilesContentsAllRaw,
+    config_files: &[&Path],
+    settings: &str,
+) -> u64 {
+    let mut hasher = Xxh3::new();
+    update_field(&mut hasher, settings.as_bytes());
+
+    update_count(&mut hasher, raw_data.len());
+    for (folder, files) in raw_data.iter() {
+        update_field(&mut hasher, folder.as_bytes());
+
+        update_count(&mut hasher, files.len());
+        for (file_name, contents) in files.iter() {
+            update_field(&mut hasher, file_name.as_bytes());
+            update_field(&mut hasher, contents.as_bytes());
+        }
+    }
+
+    // Missing config files are told apart from empty ones, their reports differ
+    for config_file in config_files {
+        match fetch_file_contents(config_file).await {
+            Ok(contents) => {
+                hasher.update(&[1]);
+                update_field(&mut hasher, contents.as_bytes());
+            }
+            Err(_) => hasher.update(&[0]),
+        }
+    }
+
+    hasher.digest()
+}
+
+fn update_count(hasher: &mut Xxh3, count: usize) {
+    hasher.update(&(count as u64).to_le_bytes());
+}
+
+fn update_field(hasher: &mut Xxh3, field: &[u8]) {
+    update_count(hasher, field.len());
+    hasher.update(field);
+}
+
+impl IMDB<ReadyState> {
+    // Returns the database stored in the snapshot file if it was built
+    // from the same sources. Any failure means a rebuild, so it's only logged.
+    pub async fn load_snapshot(snapshot_file: &Path, checksum: u64) -> Option<Self> {
+        const ERR_FN: &str = "::load_snapshot";
+
+        if !snapshot_file.is_file() {
+            println!(
+                "{MOD}: no snapshot found at '{}', building from source files.",
+                snapshot_file.display()
+            );
+            return None;
+        }
+
+        let now = Instant::now();
+
+        match Self::decode_snapshot(snapshot_file, checksum).await {
+            Ok(mut me) => {
+                me.ingestion_report.loaded_from_snapshot = true;
+                println!(
+                    "{MOD}: database loaded from '{}' in {} milliseconds.",
+                    snapshot_file.display(),
+                    now.elapsed().as_millis()
+                );
+                Some(me)
+            }
+            Err(err) => {
+                eprintln!(
+                    "{ERR_PFX} {MOD}{ERR_FN}: Could not use snapshot '{}': {err:#} Continuing with a rebuild from source files...",
+                    snapshot_file.display()
+                );
+                None
+            }
+        }
+    }
+
+    async fn decode_snapshot(snapshot_file: &Path, checksum: u64) -> anyhow::Result<Self> {
+        let bytes = tokio::fs::read(snapshot_file).await?;
+        let config = bincode::config::standard();
+
+        let (header, header_len): (SnapshotHeader, usize) =
+            bincode::decode_from_slice(&bytes, config)?;
+
+        if header.version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::VersionMismatch(header.version).into());
+        }
+        if header.checksum != checksum {
+            return Err(SnapshotError::SourcesChanged.into());
+        }
+
+        let (me, _): (Self, usize) = bincode::decode_from_slice(&bytes[header_len..], config)?;
+
+        Ok(me)
+    }
+
+    // A failed write only costs the next startup a rebuild, so it's a soft error.
+    pub async fn write_snapshot(&self, snapshot_file: &Path, checksum: u64) {
+        const ERR_FN: &str = "::write_snapshot";
+
+        if let Err(err) = self.encode_snapshot(snapshot_file, checksum).await {
+            eprintln!(
+                "{ERR_PFX} {MOD}{ERR_FN}: Could not write snapshot '{}': {err:#}. Continuing without it...",
+                snapshot_file.display()
+            );
+            return;
+        }
+
+        println!("{MOD}: snapshot written to '{}'.", snapshot_file.display());
+    }
+
+    async fn encode_snapshot(&self, snapshot_file: &Path, checksum: u64) -> anyhow::Result<()> {
+        let config = bincode::config::standard();
+        let header = SnapshotHeader {
+            version: SNAPSHOT_VERSION,
+            checksum,
+        };
+
+        let mut bytes = bincode::encode_to_vec(header, config)?;
+        bincode::encode_into_std_write(self, &mut bytes, config)?;
+
+        // Written next to the target first, so a crash mid write can't leave a truncated snapshot
+        let tmp_file = snapshot_file.with_extension("tmp");
+        tokio::fs::write(&tmp_file, bytes)
+            .await
+            .with_context(|| format!("Writing '{}' failed", tmp_file.display()))?;
+        tokio::fs::rename(&tmp_file, snapshot_file)
+            .await
+            .with_context(|| format!("Renaming '{}' failed", tmp_file.display()))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn checksum(folders: &[(&str, &[(&str, &str)])], settings: &str) -> u64 {
+        let raw_data = folders
+            .iter()
+            .map(|(folder, files)| {
+                let files = files
+                    .iter()
+                    .map(|(file_name, contents)| (file_name.to_string(), contents.to_string()))
+                    .collect();
+                (folder.to_string(), files)
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(source_checksum(&raw_data, &[], settings))
+    }
+
+    #[test]
+    fn fields_are_delimited() {
+        let base = checksum(&[("2024-25", &[("en.1.json", "{}")])], "KeepFirst");
+
+        assert_eq!(
+            base,
+            checksum(&[("2024-25", &[("en.1.json", "{}")])], "KeepFirst")
+        );
+        assert_ne!(
+            base,
+            checksum(&[("2024-25", &[("en.1.json{", "}")])], "KeepFirst")
+        );
+        assert_ne!(
+            base,
+            checksum(&[("2024-2", &[("5en.1.json", "{}")])], "KeepFirst")
+        );
+        assert_ne!(
+            base,
+            checksum(&[("2024-25", &[("en.1.json", "{}")])], "KeepFirst2024-25")
+        );
+        assert_ne!(
+            checksum(&[("2024-25", &[("a", "b"), ("c", "d")])], ""),
+            checksum(&[("2024-25", &[("a", "b")]), ("c", &[("d", "")])], "")
+        );
+    }
+}
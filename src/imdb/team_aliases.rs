@@ -0,0 +1,74 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use bincode::{Decode, Encode};
+
+use super::ingestion_report::IngestionReport;
+use super::json_fetcher::load_optional_config;
+
+const MOD: &str = "TEAM_ALIASES";
+
+// Alias file format: canonical team name as key, list of its aliases as value.
+type TeamAliasFileRaw = BTreeMap<String, Vec<String>>;
+type TeamAliasCanonicalMap = HashMap<String, String>;
+
+// Folds team name variants into one canonical team name.
+// Rules are applied first, then the alias mapping file is consulted.
+#[derive(Default, Encode, Decode)]
+pub struct TeamAliasResolver {
+    alias_canonical_map: TeamAliasCanonicalMap,
+}
+
+impl TeamAliasResolver {
+    pub async fn init(alias_file: &Path, report: &mut IngestionReport) -> Self {
+        // Alias file is optional. Without it only the rules are applied.
+        let raw =
+            load_optional_config::<TeamAliasFileRaw>(alias_file, "team alias file", report).await;
+
+        Self::from_raw(raw, alias_file)
+    }
+
+    fn from_raw(raw: TeamAliasFileRaw, alias_file: &Path) -> Self {
+        let mut alias_canonical_map = TeamAliasCanonicalMap::new();
+
+        for (canonical, aliases) in raw.into_iter() {
+            for alias in aliases.into_iter() {
+                alias_canonical_map.insert(alias, canonical.clone());
+            }
+        }
+
+        println!(
+            "{MOD}: {} team aliases loaded from '{}'.",
+            alias_canonical_map.len(),
+            alias_file.display()
+        );
+
+        Self {
+            alias_canonical_map,
+        }
+    }
+
+    pub fn resolve<'a>(&'a self, team_name: &'a str) -> &'a str {
+        let stripped = strip_country_suffix(team_name);
+
+        self.alias_canonical_map
+            .get(stripped)
+            .map(|canonical| canonical.as_str())
+            .unwrap_or(stripped)
+    }
+}
+
+// Continental competition files suffix team names with a country code like in
+// "Bayern München (GER)". The code is always three upper case letters.
+fn strip_country_suffix(team_name: &str) -> &str {
+    let Some(stripped) = team_name.strip_suffix(')') else {
+        return team_name;
+    };
+
+    match stripped.rsplit_once(" (") {
+        Some((name, code)) if code.len() == 3 && code.chars().all(|ch| ch.is_ascii_uppercase()) => {
+            name
+        }
+        _ => team_name,
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use bincode::{Decode, Encode};
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveTime};
+use chrono_tz::Tz;
+
+use super::ingestion_report::IngestionReport;
+use super::json_fetcher::load_optional_config;
+use super::tournament_meta::TournamentMeta;
+
+use crate::constants::ERR_PFX;
+
+const MOD: &str = "TIMEZONES";
+
+// Timezone file format: tournament code like 'uefa.cl' or region like 'en'
+// as key, IANA timezone name as value.
+type TimezoneFileRaw = BTreeMap<String, String>;
+
+// Default timezones of tournaments. Kickoff times in the data are local times
+// of the tournament, so they're read in the timezone found for its code,
+// then for its region. Tournaments without either are in UTC.
+#[derive(Default, Encode, Decode)]
+pub struct TimezoneTable {
+    #[bincode(with_serde)]
+    key_timezone_map: HashMap<String, Tz>,
+}
+
+impl TimezoneTable {
+    pub async fn init(timezone_file: &Path, report: &mut IngestionReport) -> Self {
+        const ERR_FN: &str = "::init";
+
+        // Timezone file is optional. Without it every tournament is in UTC.
+        let raw =
+            load_optional_config::<TimezoneFileRaw>(timezone_file, "timezone file", report).await;
+
+        let mut key_timezone_map = HashMap::new();
+        for (key, timezone) in raw.into_iter() {
+            match timezone.parse::<Tz>() {
+                Ok(timezone) => {
+                    key_timezone_map.insert(key, timezone);
+                }
+                Err(err) => {
+                    let message = format!(
+                        "Timezone of '{key}' in '{}' is not valid: {err}.",
+                        timezone_file.display()
+                    );
+                    eprintln!("{ERR_PFX} {MOD}{ERR_FN}: {message} Continuing without it...");
+                    report.warning(message);
+                }
+            }
+        }
+
+        Self { key_timezone_map }
+    }
+
+    pub fn resolve(&self, meta: Option<&TournamentMeta>) -> Tz {
+        meta.and_then(|meta| {
+            self.key_timezone_map
+                .get(&meta.code)
+                .or_else(|| self.key_timezone_map.get(&meta.region))
+        })
+        .copied()
+        .unwrap_or(Tz::UTC)
+    }
+}
+
+// Local kickoff as a timestamp. When clocks go back the local time happens twice,
+// then the earlier one is taken. Local times skipped by clocks going forward don't exist.
+pub fn kickoff(
+    date: NaiveDate,
+    time: Option<NaiveTime>,
+    timezone: Tz,
+) -> Option<DateTime<FixedOffset>> {
+    date.and_time(time?)
+        .and_local_timezone(timezone)
+        .earliest()
+        .map(|kickoff| kickoff.fixed_offset())
+}
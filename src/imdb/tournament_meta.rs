@@ -0,0 +1,121 @@
+use bincode::{Decode, Encode};
+use serde::{Deserialize, Serialize};
+
+use super::json_fetcher::DATA_FILE_EXTENSIONS;
+
+// Continental competition file prefixes and the confederations they belong to.
+// For example 'uefa.cl.json' or 'copa.l.json' (Copa Libertadores).
+const CONTINENTAL_PREFIXES: [(&str, &str); 6] = [
+    ("uefa", "uefa"),
+    ("copa", "conmebol"),
+    ("conmebol", "conmebol"),
+    ("concacaf", "concacaf"),
+    ("caf", "caf"),
+    ("afc", "afc"),
+];
+
+// Leagues whose file names don't follow the '<country>.<tier>' format.
+const SINGLE_TOKEN_LEAGUES: [(&str, &str, u8); 1] = [("mls", "us", 1)];
+
+// Countries of the national regions, so tournaments can be searched by country.
+const REGION_COUNTRIES: [(&str, &str); 27] = [
+    ("ar", "Argentina"),
+    ("at", "Austria"),
+    ("au", "Australia"),
+    ("be", "Belgium"),
+    ("br", "Brazil"),
+    ("ch", "Switzerland"),
+    ("cn", "China"),
+    ("co", "Colombia"),
+    ("cz", "Czech Republic"),
+    ("de", "Germany"),
+    ("dz", "Algeria"),
+    ("eg", "Egypt"),
+    ("en", "England"),
+    ("es", "Spain"),
+    ("fr", "France"),
+    ("gr", "Greece"),
+    ("hu", "Hungary"),
+    ("it", "Italy"),
+    ("jp", "Japan"),
+    ("ma", "Morocco"),
+    ("mx", "Mexico"),
+    ("nl", "Netherlands"),
+    ("pt", "Portugal"),
+    ("ru", "Russia"),
+    ("sco", "Scotland"),
+    ("tr", "Turkey"),
+    ("us", "United States"),
+];
+
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Encode, Decode,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum CompetitionKind {
+    League,
+    Cup,
+    Continental,
+}
+
+// Tournament metadata derived from openfootball data file names
+// like 'en.1.json', 'de.cup.json', 'uefa.cl.json', 'en.1.txt' or 'en.1.csv'.
+#[derive(Debug, Clone, Serialize, PartialEq, Eq, PartialOrd, Ord, Encode, Decode)]
+pub struct TournamentMeta {
+    pub code: String,
+    pub region: String,
+    pub kind: CompetitionKind,
+    pub tier: Option<u8>,
+    // Filled in from the timezone table when the database is built
+    pub timezone: Option<String>,
+}
+
+impl TournamentMeta {
+    pub fn from_file_name(file_name: &str) -> Option<Self> {
+        let code = DATA_FILE_EXTENSIONS
+            .iter()
+            .find_map(|extension| file_name.strip_suffix(extension))
+            .unwrap_or(file_name);
+        let tokens = code.split('.').collect::<Vec<_>>();
+
+        let (region, kind, tier) = match tokens.as_slice() {
+            [prefix, _] if Self::confederation(prefix).is_some() => (
+                Self::confederation(prefix).unwrap(),
+                CompetitionKind::Continental,
+                None,
+            ),
+            [country, "cup"] => (*country, CompetitionKind::Cup, None),
+            [country, tier] => (*country, CompetitionKind::League, Some(tier.parse().ok()?)),
+            [league] => {
+                let (_, country, tier) = SINGLE_TOKEN_LEAGUES
+                    .iter()
+                    .find(|(name, _, _)| name == league)?;
+
+                (*country, CompetitionKind::League, Some(*tier))
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            code: code.to_string(),
+            region: region.to_string(),
+            kind,
+            tier,
+            timezone: None,
+        })
+    }
+
+    pub fn country_name(&self) -> Option<&'static str> {
+        REGION_COUNTRIES
+            .iter()
+            .find(|(region, _)| *region == self.region)
+            .map(|(_, country)| *country)
+    }
+
+    fn confederation(prefix: &str) -> Option<&'static str> {
+        CONTINENTAL_PREFIXES
+            .iter()
+            .find(|(pfx, _)| *pfx == prefix)
+            .map(|(_, confederation)| *confederation)
+    }
+}
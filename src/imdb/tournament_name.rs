@@ -0,0 +1,147 @@
+use super::data_types::Year;
+
+const COUNTRY_DELIMITER: &str = " | ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SeasonLabel {
+    pub start_year: Year,
+    pub end_year: Option<Year>,
+}
+
+// Parsed form of the openfootball tournament name field.
+// Names come in formats like:
+// "English Premier League 2024/25", "Algeria | Ligue 1 2024/25" or "Copa Libertadores 2025"
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TournamentName {
+    pub country: Option<String>,
+    pub competition: String,
+    pub season: Option<SeasonLabel>,
+}
+
+impl TournamentName {
+    pub fn parse(name: &str) -> Self {
+        let name = name.trim();
+
+        let (country, rest) = match name.split_once(COUNTRY_DELIMITER) {
+            Some((country, rest)) => (Some(country.trim().to_string()), rest.trim()),
+            None => (None, name),
+        };
+
+        // The season label is only dropped when it really is one,
+        // otherwise the last token belongs to the competition name.
+        let (competition, season) = match rest.rsplit_once(' ') {
+            Some((competition, last)) => match parse_season_label(last) {
+                Some(season) => (competition.trim_end(), Some(season)),
+                None => (rest, None),
+            },
+            None => (rest, None),
+        };
+
+        Self {
+            country,
+            competition: competition.to_string(),
+            season,
+        }
+    }
+
+    // Tournaments are identified by their country qualified competition name,
+    // so that same named competitions of different countries don't collide.
+    pub fn key(&self) -> String {
+        match &self.country {
+            Some(country) => format!("{country}{COUNTRY_DELIMITER}{}", self.competition),
+            None => self.competition.clone(),
+        }
+    }
+}
+
+// Season labels look like "2024/25", "2024/2025", "2024-25" or just "2025"
+fn parse_season_label(token: &str) -> Option<SeasonLabel> {
+    let (start, end) = match token.split_once(['/', '-']) {
+        Some((start, end)) => (start, Some(end)),
+        None => (token, None),
+    };
+
+    if start.len() != 4 {
+        return None;
+    }
+    let start_year = start.parse::<Year>().ok()?;
+
+    let end_year = match end {
+        None => None,
+        Some(end) if end.len() == 4 => Some(end.parse::<Year>().ok()?),
+        Some(end) if end.len() == 2 => {
+            let century = (start_year / 100) * 100;
+            let end_year = century + end.parse::<Year>().ok()?;
+
+            // Seasons spanning a century change like 1999/00
+            Some(if end_year < start_year {
+                end_year + 100
+            } else {
+                end_year
+            })
+        }
+        Some(_) => return None,
+    };
+
+    Some(SeasonLabel {
+        start_year,
+        end_year,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn season(start_year: Year, end_year: Option<Year>) -> Option<SeasonLabel> {
+        Some(SeasonLabel {
+            start_year,
+            end_year,
+        })
+    }
+
+    #[test]
+    fn splits_country_competition_and_season() {
+        let name = TournamentName::parse("Algeria | Ligue 1 2024/25");
+        assert_eq!(name.country.as_deref(), Some("Algeria"));
+        assert_eq!(name.competition, "Ligue 1");
+        assert_eq!(name.season, season(2024, Some(2025)));
+        assert_eq!(name.key(), "Algeria | Ligue 1");
+
+        let name = TournamentName::parse("  English Premier League 2024/2025 ");
+        assert_eq!(name.country, None);
+        assert_eq!(name.competition, "English Premier League");
+        assert_eq!(name.season, season(2024, Some(2025)));
+        assert_eq!(name.key(), "English Premier League");
+
+        let name = TournamentName::parse("Copa Libertadores 2025");
+        assert_eq!(name.competition, "Copa Libertadores");
+        assert_eq!(name.season, season(2025, None));
+    }
+
+    #[test]
+    fn two_digit_seasons_follow_the_start_year() {
+        assert_eq!(parse_season_label("2017-18"), season(2017, Some(2018)));
+        assert_eq!(parse_season_label("1999/00"), season(1999, Some(2000)));
+        assert_eq!(parse_season_label("2099/00"), season(2099, Some(2100)));
+    }
+
+    #[test]
+    fn keeps_tokens_that_are_not_seasons() {
+        for name in [
+            "Ligue 1",
+            "Serie A",
+            "Premier League 25",
+            "Cup 2024/5",
+            "Cup 24/25",
+        ] {
+            let parsed = TournamentName::parse(name);
+            assert_eq!(parsed.competition, name);
+            assert_eq!(parsed.season, None);
+        }
+
+        let name = TournamentName::parse("Bundesliga");
+        assert_eq!(name.competition, "Bundesliga");
+        assert_eq!(name.season, None);
+    }
+}
@@ -9,11 +9,14 @@ mod env;
 use env::AppEnvVars;
 
 mod imdb;
+use imdb::ingestion_report::FailedIngestion;
 mod rest_api;
 
 mod appstate;
 use appstate::AppState;
 
+mod reloader;
+
 pub mod constants;
 use constants::ERR_PFX;
 
@@ -37,8 +40,39 @@ async fn init_app() -> anyhow::Result<(Router, Arc<AppEnvVars>)> {
     ))
 }
 
+// Builds the database like the server would and writes the ingestion report as json.
+// Build logs go to stdout, so the report always goes to a file. It's also written when
+// the build fails, which then still exits with the error.
+// Usage: football-emporium ingestion-report <output_file>
+async fn write_ingestion_report(output_file: &str) -> anyhow::Result<()> {
+    const ERR_FN: &str = "::write_ingestion_report";
+    let env_vars = AppEnvVars::init()?;
+    let (report, result) = match imdb::IMDB::init(&env_vars).await {
+        Ok(imdb) => (serde_json::to_string_pretty(imdb.ingestion_report())?, Ok(())),
+        Err(err) => match err.downcast_ref::<FailedIngestion>() {
+            Some(FailedIngestion(report)) => (serde_json::to_string_pretty(report)?, Err(err)),
+            None => return Err(err),
+        },
+    };
+
+    std::fs::write(output_file, report)
+        .with_context(|| format!("{ERR_PFX} {MOD}{ERR_FN}: Could not write report to '{output_file}'."))?;
+
+    result
+}
+
 #[tokio::main]
 async fn main() {
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("ingestion-report") {
+        let Some(output_file) = args.next() else {
+            eprintln!("{ERR_PFX} {MOD}: Usage: football-emporium ingestion-report <output_file>");
+            std::process::exit(2);
+        };
+        write_ingestion_report(&output_file).await.unwrap();
+        return;
+    }
+
     let (app, env_vars) = init_app().await.unwrap();
 
     let host_address: &str = env_vars.host_address.as_ref();
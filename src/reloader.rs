@@ -0,0 +1,122 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+
+use crate::env::AppEnvVars;
+use crate::imdb::{IMDB, IMDBShared};
+
+use crate::constants::ERR_PFX;
+const MOD: &str = "RELOADER";
+
+// Data drops usually touch many files at once.
+// We wait until the data roots have been quiet for this long before rebuilding.
+const DEBOUNCE: Duration = Duration::from_secs(2);
+
+// Watches the data roots and rebuilds the database when its contents change.
+// The new database is swapped in atomically. Requests that already hold
+// the old snapshot finish their work on it.
+pub fn spawn_reloader(imdb: IMDBShared, env_vars: Arc<AppEnvVars>) -> anyhow::Result<()> {
+    const ERR_FN: &str = "::spawn_reloader";
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<notify::Result<Event>>();
+
+    let mut watcher = notify::recommended_watcher(move |event| {
+        // Receiver only goes away when the reloader task ends
+        let _ = tx.send(event);
+    })
+    .with_context(|| format!("{ERR_PFX} {MOD}{ERR_FN}: Could not create file watcher."))?;
+
+    for data_root in env_vars.data_roots.iter() {
+        // A missing data root is skipped by the json fetcher too
+        if let Err(err) = watcher.watch(data_root, RecursiveMode::Recursive) {
+            eprintln!(
+                "{ERR_PFX} {MOD}{ERR_FN}: Could not watch data root '{}': {err}. Continuing without it...",
+                data_root.display()
+            );
+            continue;
+        }
+
+        println!("{MOD}: watching '{}' for changes.", data_root.display());
+    }
+
+    tokio::spawn(async move {
+        // Watcher stops when dropped so it lives as long as this task.
+        let _watcher = watcher;
+
+        while let Some(event) = rx.recv().await {
+            if !is_relevant(event) {
+                continue;
+            }
+
+            loop {
+                match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                    Ok(Some(_)) => continue,
+                    Ok(None) => return,
+                    Err(_) => break,
+                }
+            }
+
+            reload(&imdb, env_vars.clone()).await;
+        }
+    });
+
+    Ok(())
+}
+
+fn is_relevant(event: notify::Result<Event>) -> bool {
+    const ERR_FN: &str = "::is_relevant";
+
+    match event {
+        Ok(event) => matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ),
+        Err(err) => {
+            eprintln!("{ERR_PFX} {MOD}{ERR_FN}: File watcher error: {err}");
+            false
+        }
+    }
+}
+
+async fn reload(imdb: &IMDBShared, env_vars: Arc<AppEnvVars>) {
+    const ERR_FN: &str = "::reload";
+
+    println!("{MOD}: change detected in data roots, rebuilding database.");
+    let now = Instant::now();
+
+    // Building is CPU heavy and would hold up a runtime worker until it's done,
+    // so it gets a blocking thread. File reads in there still go through the runtime.
+    let handle = tokio::runtime::Handle::current();
+    let build = tokio::task::spawn_blocking(move || handle.block_on(IMDB::init(&env_vars)));
+
+    match build
+        .await
+        .context("Build task failed")
+        .and_then(|result| result)
+    {
+        Ok(new_imdb) => {
+            let diff = imdb.load().diff(&new_imdb);
+            imdb.store(Arc::new(new_imdb));
+
+            if diff.is_empty() {
+                println!(
+                    "{MOD}: database rebuilt in {} milliseconds with no changes in content.",
+                    now.elapsed().as_millis()
+                );
+            } else {
+                println!(
+                    "{MOD}: database rebuilt in {} milliseconds: {diff}",
+                    now.elapsed().as_millis()
+                );
+            }
+        }
+        Err(err) => {
+            eprintln!(
+                "{ERR_PFX} {MOD}{ERR_FN}: Rebuilding database failed: {err:#}. Continuing with the previous one..."
+            );
+        }
+    }
+}
@@ -18,9 +18,12 @@ pub fn get_router(state: AppState) -> Router {
 
     Router::new()
         .route("/seasons", get(get_seasons))
+        .route("/matches", get(get_matches))
+        .route("/matches/{id}", get(get_match_detail))
         .route("/all_matches", get(get_all_matches))
         .route("/tournaments", get(get_tournaments))
         .route("/teams", get(get_teams))
+        .route("/search", get(get_search))
         .route("/seasons/{id}", get(get_season_matches_by_id))
         .route("/tournaments/{id}", get(get_tournament_matches_by_id))
         .route(
@@ -35,12 +38,31 @@ pub fn get_router(state: AppState) -> Router {
             "/tournaments/{id}/years/{start}/{end}",
             get(get_tournament_matches_by_year_range),
         )
+        .route(
+            "/tournaments/{id}/phases/{phase}",
+            get(get_tournament_matches_by_phase),
+        )
+        .route(
+            "/tournaments/{id}/matchdays/{matchday}",
+            get(get_tournament_matches_by_matchday),
+        )
+        .route(
+            "/tournaments/{id}/matchdays/{start}/{end}",
+            get(get_tournament_matches_by_matchday_range),
+        )
+        .route(
+            "/tournaments/{id}/seasons/{season_id}/scorers",
+            get(get_tournament_season_scorers),
+        )
         .route("/years/{year}", get(get_yearly_matches_by_year))
         .route(
             "/years/{start}/{end}",
             get(get_yearly_matches_by_year_range),
         )
         .route("/teams/{id}", get(get_team_matches_by_id))
+        .route("/teams/{id}/aliases", get(get_team_aliases_by_id))
+        .route("/teams/{id}/vs/{other_id}", get(get_head_to_head))
+        .route("/team_names/{name}", get(get_team_aliases_by_name))
         .route(
             "/teams/{id}/seasons/{season_id}",
             get(get_team_matches_by_season_id),
@@ -66,6 +88,12 @@ pub fn get_router(state: AppState) -> Router {
             "/teams/{id}/tournaments/{tour_id}/years/{year_start}/{year_end}",
             get(get_team_tournament_matches_by_year_range),
         )
+        .route("/players/{id}/goals", get(get_player_goals))
+        .route("/venues", get(get_venues))
+        .route("/venues/{id}/matches", get(get_venue_matches_by_id))
+        .route("/statuses/{status}", get(get_status_matches))
+        .route("/phases/{phase}", get(get_phase_matches))
+        .route("/admin/ingestion", get(get_ingestion_report))
         .layer(ServiceBuilder::new().layer(CorsLayer::new().allow_origin(allowed_origins)))
         .with_state(state)
 }
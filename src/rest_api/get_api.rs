@@ -1,3 +1,7 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::sync::Arc;
+
 use axum::{
     Json,
     extract::{Path, Query, State},
@@ -7,9 +11,17 @@ use serde_json::{Value, json};
 
 use crate::imdb::{
     IMDBReady,
-    data_types::{Match, SeasonId, TeamId, TournamentId, Year},
+    data_types::{
+        Match, MatchId, MatchStatus, PlayerId, SeasonId, TeamId, TournamentId, VenueId, Year,
+    },
+    match_query::{MatchIter, MatchQuery},
+    round_info::{Matchday, Phase},
 };
 
+// Only named in `debug_handler`, which expands to nothing in release builds
+#[cfg(debug_assertions)]
+use crate::appstate::AppState;
+use crate::env::AppEnvVars;
 use crate::rest_api::{query_types::*, response_types::*};
 
 #[axum::debug_handler]
@@ -18,8 +30,16 @@ pub async fn get_seasons(State(db): State<IMDBReady>) -> Json<Value> {
 }
 
 #[axum::debug_handler]
-pub async fn get_tournaments(State(db): State<IMDBReady>) -> Json<Value> {
-    Json(json!(db.tournaments()))
+pub async fn get_ingestion_report(State(db): State<IMDBReady>) -> Json<Value> {
+    Json(json!(db.ingestion_report()))
+}
+
+#[axum::debug_handler]
+pub async fn get_tournaments(
+    Query(q_params): Query<TournamentQueryParams>,
+    State(db): State<IMDBReady>,
+) -> Json<Value> {
+    Json(json!(db.tournaments(&q_params)))
 }
 
 #[axum::debug_handler]
@@ -28,281 +48,908 @@ pub async fn get_teams(State(db): State<IMDBReady>) -> Json<Value> {
 }
 
 #[axum::debug_handler]
-pub async fn get_all_matches(
+pub async fn get_match_detail(
+    Path(id): Path<MatchId>,
+    State(db): State<IMDBReady>,
+) -> Result<Json<Value>, StatusCode> {
+    db.match_detail(&id).map(|detail| Json(json!(detail)))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_search(
+    Query(q_params): Query<SearchQueryParams>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    const DEFAULT_LIMIT: usize = 10;
+
+    let limit = checked_limit(q_params.limit, DEFAULT_LIMIT, &env_vars)?;
+    Ok(Json(json!(db.search(&q_params.q, q_params.kind, limit))))
+}
+
+#[axum::debug_handler]
+pub async fn get_venues(State(db): State<IMDBReady>) -> Json<Value> {
+    Json(json!(db.venues()))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_venue_matches_by_id(
     Query(q_params): Query<QueryParams>,
+    Path(id): Path<VenueId>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.all_matches().map(|(total, it)| {
-        Json(json!(MatchListResponse {
-            total,
-            list: paginate_matches(&q_params, it),
-        }))
-    })
+    let it = db.venue_matches_by_id(&id)?;
+    match_list_response(&db, &env_vars, it.size_hint().1.unwrap_or(0), &q_params, it)
+        .map(|response| Json(json!(response)))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_tournament_season_scorers(
+    Query(q_params): Query<ScorerQueryParams>,
+    Path((tour_id, season_id)): Path<(TournamentId, SeasonId)>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    const DEFAULT_LIMIT: usize = 25;
+
+    let limit = checked_limit(q_params.limit, DEFAULT_LIMIT, &env_vars)?;
+    db.tournament_season_scorers(&tour_id, &season_id, limit)
+        .map(|scorers| Json(json!(scorers)))
 }
 
 #[axum::debug_handler]
+pub async fn get_player_goals(
+    Path(id): Path<PlayerId>,
+    State(db): State<IMDBReady>,
+) -> Result<Json<Value>, StatusCode> {
+    db.player_goals(&id).map(|goals| Json(json!(goals)))
+}
+
+#[axum::debug_handler]
+pub async fn get_team_aliases_by_id(
+    Path(id): Path<TeamId>,
+    State(db): State<IMDBReady>,
+) -> Result<Json<Value>, StatusCode> {
+    db.team_aliases_by_id(&id).map(|team| Json(json!(team)))
+}
+
+#[axum::debug_handler]
+pub async fn get_team_aliases_by_name(
+    Path(name): Path<String>,
+    State(db): State<IMDBReady>,
+) -> Result<Json<Value>, StatusCode> {
+    db.team_aliases_by_name(&name).map(|team| Json(json!(team)))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_matches(
+    Query(q_params): Query<QueryParams>,
+    Query(m_params): Query<MatchQueryParams>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    query_response(&db, &env_vars, m_params.query(), &q_params)
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_all_matches(
+    Query(q_params): Query<QueryParams>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    query_response(&db, &env_vars, MatchQuery::new(), &q_params)
+}
+
+#[axum::debug_handler(state = AppState)]
 pub async fn get_season_matches_by_id(
     Query(q_params): Query<QueryParams>,
     Path(id): Path<SeasonId>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.season_matches_by_id(&id).map(|it| {
-        Json(json!(MatchListResponse {
-            total: it.size_hint().1.unwrap_or(0),
-            list: paginate_matches(&q_params, it)
-        }))
-    })
+    query_response(&db, &env_vars, MatchQuery::new().season(id), &q_params)
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_yearly_matches_by_year(
     Query(q_params): Query<QueryParams>,
     Path(year): Path<Year>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.yearly_matches_by_year(&year).map(|it| {
-        Json(json!(MatchListResponse {
-            total: it.size_hint().1.unwrap_or(0),
-            list: paginate_matches(&q_params, it)
-        }))
-    })
+    query_response(&db, &env_vars, MatchQuery::new().year(year), &q_params)
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_yearly_matches_by_year_range(
     Query(q_params): Query<QueryParams>,
     Path((year_start, year_end)): Path<(Year, Year)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.yearly_matches_year_range(&year_start, &year_end)
-        .map(|(total, it)| {
-            Json(json!(MatchListResponse {
-                total,
-                list: paginate_matches(&q_params, it),
-            }))
-        })
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new().year_range(year_start, year_end),
+        &q_params,
+    )
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_tournament_matches_by_id(
     Query(q_params): Query<QueryParams>,
     Path(tour_id): Path<TournamentId>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.tournament_matches_by_id(&tour_id).map(|it| {
-        Json(json!(MatchListResponse {
-            total: it.size_hint().1.unwrap_or(0),
-            list: paginate_matches(&q_params, it)
-        }))
-    })
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new().tournament(tour_id),
+        &q_params,
+    )
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_tournament_matches_by_season_id(
     Query(q_params): Query<QueryParams>,
     Path((tour_id, season_id)): Path<(TournamentId, SeasonId)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.tournament_matches_by_season_id(&tour_id, &season_id)
-        .map(|it| {
-            Json(json!(MatchListResponse {
-                total: it.size_hint().1.unwrap_or(0),
-                list: paginate_matches(&q_params, it)
-            }))
-        })
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new().tournament(tour_id).season(season_id),
+        &q_params,
+    )
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_tournament_matches_by_year(
     Query(q_params): Query<QueryParams>,
     Path((tour_id, year)): Path<(TournamentId, Year)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.tournament_matches_by_year(&tour_id, &year).map(|it| {
-        Json(json!(MatchListResponse {
-            total: it.size_hint().1.unwrap_or(0),
-            list: paginate_matches(&q_params, it)
-        }))
-    })
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new().tournament(tour_id).year(year),
+        &q_params,
+    )
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_tournament_matches_by_year_range(
     Query(q_params): Query<QueryParams>,
     Path((tour_id, year_start, year_end)): Path<(TournamentId, Year, Year)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.tournament_yearly_matches_year_range(&tour_id, &year_start, &year_end)
-        .map(|(total, it)| {
-            Json(json!(MatchListResponse {
-                total,
-                list: paginate_matches(&q_params, it),
-            }))
-        })
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new()
+            .tournament(tour_id)
+            .year_range(year_start, year_end),
+        &q_params,
+    )
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_matches_by_id(
     Query(q_params): Query<QueryParams>,
     Path(id): Path<TeamId>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_matches_by_id(&id, &q_params.home_away.unwrap_or(HomeAwayOption::Both))
-        .map(|(total, it)| {
-            Json(json!(MatchListResponse {
-                total,
-                list: paginate_matches(&q_params, it),
-            }))
-        })
+    query_response(&db, &env_vars, MatchQuery::new().team(id), &q_params)
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
+pub async fn get_head_to_head(
+    Query(q_params): Query<QueryParams>,
+    Query(h_params): Query<HeadToHeadQueryParams>,
+    Path((id, other_id)): Path<(TeamId, TeamId)>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    let query = h_params.query(id, other_id);
+
+    Ok(Json(json!(HeadToHeadResponse {
+        summary: db.head_to_head(&id, &other_id, &query)?,
+        matches: query_match_list(&db, &env_vars, query, &q_params)?,
+    })))
+}
+
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_matches_by_season_id(
     Query(q_params): Query<QueryParams>,
     Path((team_id, season_id)): Path<(TeamId, SeasonId)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_matches_by_season_id(
-        &team_id,
-        &season_id,
-        &q_params.home_away.unwrap_or(HomeAwayOption::Both),
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new().team(team_id).season(season_id),
+        &q_params,
     )
-    .map(|(total, it)| {
-        Json(json!(MatchListResponse {
-            total,
-            list: paginate_matches(&q_params, it),
-        }))
-    })
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_matches_by_year(
     Query(q_params): Query<QueryParams>,
     Path((team_id, year)): Path<(TeamId, Year)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_matches_by_year(
-        &team_id,
-        &year,
-        &q_params.home_away.unwrap_or(HomeAwayOption::Both),
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new().team(team_id).year(year),
+        &q_params,
     )
-    .map(|(total, it)| {
-        Json(json!(MatchListResponse {
-            total,
-            list: paginate_matches(&q_params, it),
-        }))
-    })
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_matches_by_year_range(
     Query(q_params): Query<QueryParams>,
     Path((team_id, year_start, year_end)): Path<(TeamId, Year, Year)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_matches_year_range(
-        &team_id,
-        &year_start,
-        &year_end,
-        &q_params.home_away.unwrap_or(HomeAwayOption::Both),
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new()
+            .team(team_id)
+            .year_range(year_start, year_end),
+        &q_params,
     )
-    .map(|(total, it)| {
-        Json(json!(MatchListResponse {
-            total,
-            list: paginate_matches(&q_params, it),
-        }))
-    })
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_matches_by_tournament_id(
     Query(q_params): Query<QueryParams>,
     Path((team_id, tour_id)): Path<(TeamId, TournamentId)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_matches_by_tournament_id(
-        &team_id,
-        &tour_id,
-        &q_params.home_away.unwrap_or(HomeAwayOption::Both),
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new().team(team_id).tournament(tour_id),
+        &q_params,
     )
-    .map(|(total, it)| {
-        Json(json!(MatchListResponse {
-            total,
-            list: paginate_matches(&q_params, it),
-        }))
-    })
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_tournament_matches_by_season_id(
     Query(q_params): Query<QueryParams>,
     Path((team_id, tour_id, season_id)): Path<(TeamId, TournamentId, SeasonId)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_tournament_matches_by_season_id(
-        &team_id,
-        &tour_id,
-        &season_id,
-        &q_params.home_away.unwrap_or(HomeAwayOption::Both),
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new()
+            .team(team_id)
+            .tournament(tour_id)
+            .season(season_id),
+        &q_params,
     )
-    .map(|it| {
-        Json(json!(MatchListResponse {
-            total: it.size_hint().1.unwrap_or(0),
-            list: paginate_matches(&q_params, it)
-        }))
-    })
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_tournament_matches_by_year(
     Query(q_params): Query<QueryParams>,
     Path((team_id, tour_id, year)): Path<(TeamId, TournamentId, Year)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_tournament_matches_by_year(
-        &team_id,
-        &tour_id,
-        &year,
-        &q_params.home_away.unwrap_or(HomeAwayOption::Both),
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new()
+            .team(team_id)
+            .tournament(tour_id)
+            .year(year),
+        &q_params,
     )
-    .map(|it| {
-        Json(json!(MatchListResponse {
-            total: it.size_hint().1.unwrap_or(0),
-            list: paginate_matches(&q_params, it)
-        }))
-    })
 }
 
-#[axum::debug_handler]
+#[axum::debug_handler(state = AppState)]
 pub async fn get_team_tournament_matches_by_year_range(
     Query(q_params): Query<QueryParams>,
     Path((team_id, tour_id, year_start, year_end)): Path<(TeamId, TournamentId, Year, Year)>,
     State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
 ) -> Result<Json<Value>, StatusCode> {
-    db.team_tournament_matches_by_year_range(
-        &team_id,
-        &tour_id,
-        &year_start,
-        &year_end,
-        &q_params.home_away.unwrap_or(HomeAwayOption::Both),
+    query_response(
+        &db,
+        &env_vars,
+        MatchQuery::new()
+            .team(team_id)
+            .tournament(tour_id)
+            .year_range(year_start, year_end),
+        &q_params,
     )
-    .map(|(total, it)| {
-        Json(json!(MatchListResponse {
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_status_matches(
+    Query(q_params): Query<QueryParams>,
+    Path(status): Path<MatchStatus>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    query_response(&db, &env_vars, MatchQuery::new().status(status), &q_params)
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_phase_matches(
+    Query(q_params): Query<QueryParams>,
+    Path(phase): Path<Phase>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    db.phase_matches(&phase, &q_params.round_of)
+        .and_then(|(total, it)| match_list_response(&db, &env_vars, total, &q_params, it))
+        .map(|response| Json(json!(response)))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_tournament_matches_by_phase(
+    Query(q_params): Query<QueryParams>,
+    Path((tour_id, phase)): Path<(TournamentId, Phase)>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    db.tournament_matches_by_phase(&tour_id, &phase, &q_params.round_of)
+        .and_then(|(total, it)| match_list_response(&db, &env_vars, total, &q_params, it))
+        .map(|response| Json(json!(response)))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_tournament_matches_by_matchday(
+    Query(q_params): Query<QueryParams>,
+    Path((tour_id, matchday)): Path<(TournamentId, Matchday)>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    let it = db.tournament_matches_by_matchday(&tour_id, &matchday)?;
+    match_list_response(&db, &env_vars, it.size_hint().1.unwrap_or(0), &q_params, it)
+        .map(|response| Json(json!(response)))
+}
+
+#[axum::debug_handler(state = AppState)]
+pub async fn get_tournament_matches_by_matchday_range(
+    Query(q_params): Query<QueryParams>,
+    Path((tour_id, matchday_start, matchday_end)): Path<(TournamentId, Matchday, Matchday)>,
+    State(db): State<IMDBReady>,
+    State(env_vars): State<Arc<AppEnvVars>>,
+) -> Result<Json<Value>, StatusCode> {
+    db.tournament_matches_matchday_range(&tour_id, &matchday_start, &matchday_end)
+        .and_then(|(total, it)| match_list_response(&db, &env_vars, total, &q_params, it))
+        .map(|response| Json(json!(response)))
+}
+
+// UTILITIES
+const DEFAULT_PER_PAGE: PagPerPage = PagPerPage::Ten;
+
+// Match list routes are aliases of the match query with some criteria fixed by the path.
+fn query_response(
+    db: &IMDBReady,
+    env_vars: &AppEnvVars,
+    query: MatchQuery,
+    q_params: &QueryParams,
+) -> Result<Json<Value>, StatusCode> {
+    query_match_list(db, env_vars, query, q_params).map(|response| Json(json!(response)))
+}
+
+fn query_match_list<'a>(
+    db: &'a IMDBReady,
+    env_vars: &AppEnvVars,
+    query: MatchQuery,
+    q_params: &QueryParams,
+) -> Result<MatchListResponse<'a>, StatusCode> {
+    let mut query = query.home_away(q_params.home_away.unwrap_or_default());
+    if let Some(status) = q_params.status {
+        query = query.status(status);
+    }
+    if let Some(date) = q_params.from {
+        query = query.date_from(date);
+    }
+    if let Some(date) = q_params.to {
+        query = query.date_to(date);
+    }
+
+    if q_params.uses_cursor() {
+        let start = page_start(db, env_vars, q_params)?;
+
+        // A cursor match that's gone only has its old position, which takes comparing
+        if !q_params.has_match_filters()
+            && (start.cursor.is_none() || start.cursor_match.is_some())
+            && let Some(order) = start.order.indexed()
+            && let Some(split) = db.query_matches_split(&query, order, start.cursor_match)?
+        {
+            return Ok(split_cursor_page(db, q_params, &start, split));
+        }
+
+        let (_, it) = db.query_matches(&query)?;
+        return Ok(cursor_page(db, q_params, &start, it));
+    }
+
+    let (total, it) = db.query_matches(&query)?;
+    match_list_response(db, env_vars, total, q_params, it)
+}
+
+fn match_list_response<'a>(
+    db: &'a IMDBReady,
+    env_vars: &AppEnvVars,
+    total: usize,
+    q_params: &QueryParams,
+    it: impl Iterator<Item = &'a Match>,
+) -> Result<MatchListResponse<'a>, StatusCode> {
+    if q_params.uses_cursor() {
+        let start = page_start(db, env_vars, q_params)?;
+        return Ok(cursor_page(db, q_params, &start, it));
+    }
+
+    if !q_params.has_match_filters() && q_params.sort.is_none() {
+        return Ok(MatchListResponse {
             total,
-            list: paginate_matches(&q_params, it),
-        }))
+            list: convert_kickoffs(q_params, paginate_matches(q_params, it)),
+            next: None,
+            prev: None,
+        });
+    }
+
+    // Filtered totals can't be known beforehand so we collect the references first.
+    let mut filtered = it
+        .filter(|mch| q_params.filter_match(mch))
+        .collect::<Vec<&Match>>();
+    let total = filtered.len();
+
+    if q_params.sort.is_some() {
+        sort_page_start(&q_params.match_order(), page_end(q_params), &mut filtered);
+    }
+
+    Ok(MatchListResponse {
+        total,
+        list: convert_kickoffs(q_params, paginate_matches(q_params, filtered.into_iter())),
+        next: None,
+        prev: None,
     })
 }
 
-// UTILITIES
+// Where a cursor page starts, from the cursor and limit of the query parameters
+struct PageStart<'a> {
+    limit: usize,
+    order: MatchOrder,
+    direction: CursorDirection,
+    cursor: Option<MatchCursor>,
+    // The match the cursor points at in this build of the database, if it's still in it
+    cursor_match: Option<&'a Match>,
+    cursor_position: Option<MatchPosition>,
+}
+
+// A limit of the query parameters, or the default when it's left out
+fn checked_limit(
+    limit: Option<usize>,
+    default: usize,
+    env_vars: &AppEnvVars,
+) -> Result<usize, StatusCode> {
+    let limit = limit.unwrap_or(default);
+    if limit == 0 || limit > env_vars.max_page_limit {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(limit)
+}
+
+fn page_start<'a>(
+    db: &'a IMDBReady,
+    env_vars: &AppEnvVars,
+    q_params: &QueryParams,
+) -> Result<PageStart<'a>, StatusCode> {
+    if q_params.offset.is_some() || q_params.per_page.is_some() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let limit = checked_limit(q_params.limit, DEFAULT_PER_PAGE as usize, env_vars)?;
+
+    let order = q_params.match_order();
+    let cursor = q_params
+        .cursor
+        .as_deref()
+        .map(|cursor| MatchCursor::decode(cursor, &order).ok_or(StatusCode::BAD_REQUEST))
+        .transpose()?;
+    let cursor_match = cursor
+        .as_ref()
+        .and_then(|cursor| db.match_by_key(&cursor.mch));
+
+    Ok(PageStart {
+        limit,
+        order,
+        direction: cursor
+            .as_ref()
+            .map(|cursor| cursor.direction)
+            .unwrap_or(CursorDirection::Next),
+        cursor_position: cursor.as_ref().map(|cursor| {
+            cursor_match
+                .map(|mch| order.position(mch))
+                .unwrap_or(cursor.position)
+        }),
+        cursor,
+        cursor_match,
+    })
+}
+
+// Pages start right after or before the match the cursor points at. Finding them is
+// one pass of position comparisons however deep the page is, and only the page gets sorted.
+fn cursor_page<'a>(
+    db: &'a IMDBReady,
+    q_params: &QueryParams,
+    start: &PageStart<'a>,
+    it: impl Iterator<Item = &'a Match>,
+) -> MatchListResponse<'a> {
+    let mut total = 0;
+    let mut list = it
+        .filter(|mch| q_params.filter_match(mch))
+        .inspect(|_| total += 1)
+        .filter(|mch| {
+            start.cursor_position.is_none_or(|cursor_position| {
+                let place = start
+                    .order
+                    .compare(&start.order.position(mch), &cursor_position);
+                match start.direction {
+                    CursorDirection::Next => place == Ordering::Greater,
+                    CursorDirection::Prev => place == Ordering::Less,
+                }
+            })
+        })
+        .collect::<Vec<&Match>>();
+
+    // Matches on the other side of the cursor
+    let has_passed = total > list.len();
+    let has_more = list.len() > start.limit;
+
+    match start.direction {
+        CursorDirection::Next => sort_page_start(&start.order, start.limit, &mut list),
+        CursorDirection::Prev => sort_page_end(&start.order, start.limit, &mut list),
+    }
+
+    cursor_response(db, q_params, start, total, list, has_passed, has_more)
+}
+
+// Without filters, and in an order the indexes keep, the page is taken right from
+// the matches on its side of the cursor match. Nothing else gets looked at.
+fn split_cursor_page<'a>(
+    db: &'a IMDBReady,
+    q_params: &QueryParams,
+    start: &PageStart<'a>,
+    (total, mut before, mut after): (usize, MatchIter<'a>, MatchIter<'a>),
+) -> MatchListResponse<'a> {
+    let (mut list, has_passed) = match start.direction {
+        CursorDirection::Next => (
+            after.by_ref().take(start.limit + 1).collect::<Vec<_>>(),
+            before.next_back().is_some(),
+        ),
+        CursorDirection::Prev => {
+            let mut list = before.rev().take(start.limit + 1).collect::<Vec<_>>();
+            list.reverse();
+            (list, after.next().is_some())
+        }
+    };
+
+    let has_more = list.len() > start.limit;
+    match start.direction {
+        CursorDirection::Next => list.truncate(start.limit),
+        CursorDirection::Prev => {
+            list.drain(..list.len() - list.len().min(start.limit));
+        }
+    }
+
+    cursor_response(db, q_params, start, total, list, has_passed, has_more)
+}
+
+fn cursor_response<'a>(
+    db: &'a IMDBReady,
+    q_params: &QueryParams,
+    start: &PageStart<'a>,
+    total: usize,
+    list: Vec<&'a Match>,
+    has_passed: bool,
+    has_more: bool,
+) -> MatchListResponse<'a> {
+    let (has_next, has_prev) = match start.direction {
+        CursorDirection::Next => (has_more, has_passed),
+        CursorDirection::Prev => (has_passed, has_more),
+    };
+
+    // An empty page has no matches to point at, so the cursors stay where they were
+    let page_cursor = |direction: CursorDirection, mch: Option<&&Match>| {
+        let (position, mch) = match (mch, &start.cursor, start.cursor_position) {
+            (Some(mch), _, _) => (start.order.position(mch), db.match_key(mch)),
+            (None, Some(cursor), Some(position)) => (position, cursor.mch.clone()),
+            _ => return None,
+        };
+
+        Some(
+            MatchCursor {
+                direction,
+                position,
+                mch,
+            }
+            .encode(&start.order),
+        )
+    };
+
+    MatchListResponse {
+        total,
+        next: has_next
+            .then(|| page_cursor(CursorDirection::Next, list.last()))
+            .flatten(),
+        prev: has_prev
+            .then(|| page_cursor(CursorDirection::Prev, list.first()))
+            .flatten(),
+        list: convert_kickoffs(q_params, list),
+    }
+}
+
+// Keeps the first `count` matches in order. The rest are only partitioned, not sorted.
+fn sort_page_start(order: &MatchOrder, count: usize, list: &mut Vec<&Match>) {
+    let compare = |a: &&Match, b: &&Match| order.compare_matches(a, b);
+
+    if count == 0 {
+        list.clear();
+        return;
+    }
+    if count < list.len() {
+        list.select_nth_unstable_by(count - 1, compare);
+        list.truncate(count);
+    }
+    list.sort_unstable_by(compare);
+}
+
+// Keeps the last `count` matches in order.
+fn sort_page_end(order: &MatchOrder, count: usize, list: &mut Vec<&Match>) {
+    let compare = |a: &&Match, b: &&Match| order.compare_matches(a, b);
+
+    if count < list.len() {
+        let start = list.len() - count;
+        list.select_nth_unstable_by(start, compare);
+        list.drain(..start);
+    }
+    list.sort_unstable_by(compare);
+}
+
+fn convert_kickoffs<'a>(q_params: &QueryParams, list: Vec<&'a Match>) -> Vec<Cow<'a, Match>> {
+    let Some(tz) = q_params.tz else {
+        return list.into_iter().map(Cow::Borrowed).collect();
+    };
+
+    list.into_iter()
+        .map(|mch| {
+            let mut converted = mch.clone();
+            converted.kickoff = mch
+                .kickoff
+                .map(|kickoff| kickoff.with_timezone(&tz).fixed_offset());
+            Cow::Owned(converted)
+        })
+        .collect()
+}
+
+// Offsets come straight from the query, so huge ones saturate instead of overflowing
+fn page_end(q_params: &QueryParams) -> usize {
+    q_params
+        .offset
+        .unwrap_or(0)
+        .saturating_add(q_params.per_page.unwrap_or(DEFAULT_PER_PAGE) as usize)
+}
+
 fn paginate_matches<'a>(
     q_params: &QueryParams,
     it: impl Iterator<Item = &'a Match>,
 ) -> Vec<&'a Match> {
-    const DEFAULT_PER_PAGE: PagPerPage = PagPerPage::Ten;
-
     let offset = q_params.offset.unwrap_or(0);
     let per_page = q_params.per_page.unwrap_or(DEFAULT_PER_PAGE) as usize;
 
     it.skip(offset).take(per_page).collect::<Vec<&Match>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::imdb::IMDB;
+
+    #[test]
+    fn huge_offsets_give_empty_pages() {
+        let q_params = QueryParams {
+            offset: Some(usize::MAX),
+            sort: Some(MatchSort::Date),
+            ..Default::default()
+        };
+        assert_eq!(page_end(&q_params), usize::MAX);
+
+        let mut list: Vec<&Match> = vec![];
+        sort_page_start(&q_params.match_order(), page_end(&q_params), &mut list);
+        assert!(paginate_matches(&q_params, list.into_iter()).is_empty());
+
+        let q_params = QueryParams {
+            offset: Some(20),
+            per_page: Some(PagPerPage::Fifty),
+            ..Default::default()
+        };
+        assert_eq!(page_end(&q_params), 70);
+    }
+
+    // Ties in every sort key: three matches on one date, equal goals and margins,
+    // and a match without a score
+    const LEAGUE_MATCHES: [&str; 8] = [
+        r#"{"round": "Matchday 1", "date": "2024-08-17", "team1": "Arsenal FC", "team2": "Chelsea FC", "score": {"ft": [1, 0]}}"#,
+        r#"{"round": "Matchday 1", "date": "2024-08-17", "team1": "Fulham FC", "team2": "Everton FC", "score": {"ft": [2, 2]}}"#,
+        r#"{"round": "Matchday 1", "date": "2024-08-17", "team1": "Brentford FC", "team2": "Wolves", "score": {"ft": [0, 0]}}"#,
+        r#"{"round": "Matchday 2", "date": "2024-08-24", "team1": "Chelsea FC", "team2": "Fulham FC", "score": {"ft": [3, 1]}}"#,
+        r#"{"round": "Matchday 2", "date": "2024-08-24", "team1": "Everton FC", "team2": "Arsenal FC", "score": {}, "status": "postponed"}"#,
+        r#"{"round": "Matchday 3", "date": "2024-08-31", "team1": "Wolves", "team2": "Arsenal FC", "score": {"ft": [1, 2]}}"#,
+        r#"{"round": "Matchday 3", "date": "2024-08-31", "team1": "Brentford FC", "team2": "Chelsea FC", "score": {"ft": [2, 2]}}"#,
+        r#"{"round": "Matchday 4", "date": "2024-09-14", "team1": "Fulham FC", "team2": "Wolves", "score": {"ft": [0, 1]}}"#,
+    ];
+
+    fn test_db(root_name: &str, matches: &[&str]) -> IMDBReady {
+        let league_file = format!(
+            r#"{{"name": "English Premier League 2024/25", "matches": [{}]}}"#,
+            matches.join(",")
+        );
+        Arc::new(IMDB::from_test_files(
+            root_name,
+            &[("2024-25", &[("en.1.json", &league_file)])],
+        ))
+    }
+
+    fn query_page<'a>(
+        db: &'a IMDBReady,
+        q_params: &QueryParams,
+    ) -> Result<MatchListResponse<'a>, StatusCode> {
+        let env_vars = AppEnvVars::with_data_root(Default::default());
+        query_match_list(db, &env_vars, MatchQuery::new(), q_params)
+    }
+
+    // Team names of the matches of a list, which stay the same when ids change
+    type Fixtures = Vec<(String, String)>;
+
+    fn fixtures(list: &[Cow<Match>]) -> Fixtures {
+        list.iter()
+            .map(|mch| (mch.team1.clone(), mch.team2.clone()))
+            .collect()
+    }
+
+    // Every match of the query in the order of its sort, as offset pages have them
+    fn sorted_fixtures(db: &IMDBReady, q_params: &QueryParams) -> Fixtures {
+        let query = MatchQuery::new();
+        let (_, it) = db.query_matches(&query).unwrap();
+        let mut list = it
+            .filter(|mch| q_params.filter_match(mch))
+            .collect::<Vec<_>>();
+        list.sort_by(|a, b| q_params.match_order().compare_matches(a, b));
+
+        fixtures(&list.into_iter().map(Cow::Borrowed).collect::<Vec<_>>())
+    }
+
+    // Follows the next cursors from the first page to the last one,
+    // then the prev cursors from the last page back to the first one.
+    fn walk_pages(db: &IMDBReady, q_params: &QueryParams) -> (Fixtures, Fixtures) {
+        let mut q_params = q_params.clone();
+        let mut forward = vec![];
+        let last_page = loop {
+            let page = query_page(db, &q_params).unwrap();
+            assert!(page.list.len() <= 3);
+            forward.extend(fixtures(&page.list));
+            match &page.next {
+                Some(cursor) => q_params.cursor = Some(cursor.clone()),
+                None => break page,
+            }
+        };
+
+        let mut backward = vec![fixtures(&last_page.list)];
+        let mut prev = last_page.prev;
+        while let Some(cursor) = prev {
+            q_params.cursor = Some(cursor);
+            let page = query_page(db, &q_params).unwrap();
+            backward.push(fixtures(&page.list));
+            prev = page.prev;
+        }
+        backward.reverse();
+
+        (forward, backward.concat())
+    }
+
+    #[test]
+    fn cursor_pages_follow_the_sort() {
+        let db = test_db("cursor-pages", &LEAGUE_MATCHES);
+
+        for (sort, order) in [
+            (None, None),
+            (None, Some(SortOrder::Desc)),
+            (Some(MatchSort::Date), None),
+            (Some(MatchSort::Date), Some(SortOrder::Desc)),
+            (Some(MatchSort::Goals), Some(SortOrder::Desc)),
+            (Some(MatchSort::Margin), None),
+        ] {
+            // Without filters, id and date orders take the pages from the split of the indexes.
+            // With a filter every match is compared to the cursor, the pages must be the same.
+            for exclude_status in [None, Some(MatchStatus::Cancelled)] {
+                let q_params = QueryParams {
+                    limit: Some(3),
+                    sort,
+                    order,
+                    exclude_status,
+                    ..Default::default()
+                };
+                let expected = sorted_fixtures(&db, &q_params);
+                assert_eq!(expected.len(), LEAGUE_MATCHES.len());
+
+                let (forward, backward) = walk_pages(&db, &q_params);
+                assert_eq!(forward, expected, "{sort:?} {order:?} {exclude_status:?}");
+                assert_eq!(backward, expected, "{sort:?} {order:?} {exclude_status:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn cursor_of_a_deleted_match_keeps_its_place() {
+        let db = test_db("cursor-deleted-before", &LEAGUE_MATCHES);
+        let q_params = QueryParams {
+            limit: Some(3),
+            sort: Some(MatchSort::Date),
+            ..Default::default()
+        };
+        let page = query_page(&db, &q_params).unwrap();
+        assert_eq!(
+            fixtures(&page.list).last(),
+            Some(&("Brentford FC".to_string(), "Wolves".to_string()))
+        );
+
+        // The last match of the first page is gone, ids of the later ones move down by one
+        let mut matches = LEAGUE_MATCHES.to_vec();
+        matches.remove(2);
+        let rebuilt = test_db("cursor-deleted-after", &matches);
+        let q_params = QueryParams {
+            cursor: page.next,
+            ..q_params
+        };
+        let next_page = query_page(&rebuilt, &q_params).unwrap();
+
+        assert_eq!(
+            fixtures(&next_page.list),
+            sorted_fixtures(&rebuilt, &q_params)[2..5]
+        );
+        assert!(next_page.prev.is_some());
+    }
+
+    #[test]
+    fn cursors_of_another_sort_are_rejected() {
+        let db = test_db("cursor-other-sort", &LEAGUE_MATCHES);
+        let q_params = QueryParams {
+            limit: Some(3),
+            sort: Some(MatchSort::Date),
+            ..Default::default()
+        };
+        let page = query_page(&db, &q_params).unwrap();
+
+        let q_params = QueryParams {
+            cursor: page.next,
+            order: Some(SortOrder::Desc),
+            ..q_params
+        };
+        assert!(matches!(
+            query_page(&db, &q_params),
+            Err(StatusCode::BAD_REQUEST)
+        ));
+    }
+}
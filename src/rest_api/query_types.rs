@@ -1,6 +1,21 @@
-use serde::Deserialize;
+use std::cmp::Ordering;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use chrono::{Datelike, NaiveDate, NaiveTime, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
 use serde_repr::Deserialize_repr;
 
+use crate::imdb::{
+    data_types::{
+        Match, MatchId, MatchKey, MatchResult, MatchStatus, ScoreGoals, SearchKind, SeasonId,
+        TeamId, TournamentId, Year,
+    },
+    match_query::{IndexedOrder, MatchQuery},
+    round_info::{Matchday, Phase, RoundOf},
+    tournament_meta::{CompetitionKind, TournamentMeta},
+};
+
 #[derive(Copy, Clone, Deserialize_repr, Debug)]
 #[repr(u8)]
 pub enum PagPerPage {
@@ -11,23 +26,551 @@ pub enum PagPerPage {
     TwoHundredFifty = 250,
 }
 
-#[derive(Copy, Clone, Deserialize, Debug)]
+#[derive(Copy, Clone, Deserialize, Default, Debug)]
 #[serde(rename_all(deserialize = "lowercase"))]
 pub enum HomeAwayOption {
+    #[default]
     Both,
     Home,
     Away,
 }
 
-impl Default for HomeAwayOption {
-    fn default() -> Self {
-        Self::Both
+// Which score the score filters look at. Full time is the score after 90 minutes,
+// so extra time and penalties don't count.
+#[derive(Copy, Clone, Deserialize, Default, Debug)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum ScorePeriod {
+    #[default]
+    Ft,
+    Ht,
+}
+
+// Exact scoreline like '2-1', home goals first
+#[derive(Copy, Clone, Deserialize, Debug, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct ScoreLine(u8, u8);
+
+impl TryFrom<String> for ScoreLine {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value
+            .split_once('-')
+            .and_then(|(goals1, goals2)| Some(Self(goals1.parse().ok()?, goals2.parse().ok()?)))
+            .ok_or_else(|| format!("Score '{value}' should be in the format: 2-1."))
     }
 }
 
-#[derive(Copy, Clone, Deserialize, Default, Debug)]
+// Comma separated weekdays like 'sat,sun' or 'tuesday,wednesday'
+#[derive(Copy, Clone, Deserialize, Debug)]
+#[serde(try_from = "String")]
+pub struct Weekdays(u8);
+
+impl TryFrom<String> for Weekdays {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.split(',').try_fold(Self(0), |weekdays, weekday| {
+            let weekday = weekday
+                .trim()
+                .parse::<Weekday>()
+                .map_err(|_| format!("Weekday '{weekday}' should be like: mon, tue or monday."))?;
+            Ok(Self(weekdays.0 | 1 << weekday.num_days_from_monday()))
+        })
+    }
+}
+
+impl Weekdays {
+    fn contains(&self, weekday: Weekday) -> bool {
+        self.0 & 1 << weekday.num_days_from_monday() != 0
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Default, Debug, PartialEq, Eq)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+// Kickoff sorts by the moment of kickoff, goals and margin by the full time score.
+// Matches without a kickoff or score go last in both orders.
+#[derive(Copy, Clone, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all(deserialize = "lowercase"))]
+pub enum MatchSort {
+    Date,
+    Kickoff,
+    Goals,
+    Margin,
+}
+
+impl MatchSort {
+    // Dates are days and kickoffs unix timestamps, so every key fits in one number
+    fn key(&self, mch: &Match) -> Option<i64> {
+        match self {
+            Self::Date => Some(mch.date.num_days_from_ce() as i64),
+            Self::Kickoff => mch.kickoff.map(|kickoff| kickoff.timestamp()),
+            Self::Goals => mch
+                .score
+                .full_time
+                .as_ref()
+                .map(|score| score.total() as i64),
+            Self::Margin => mch
+                .score
+                .full_time
+                .as_ref()
+                .map(|score| score.margin() as i64),
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Date => "date",
+            Self::Kickoff => "kickoff",
+            Self::Goals => "goals",
+            Self::Margin => "margin",
+        }
+    }
+}
+
+// Place of a match in a sorted list. Ties in the sort key are broken by match id,
+// so every match has exactly one place and pages never overlap or skip.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchPosition {
+    key: Option<i64>,
+    id: MatchId,
+}
+
+// Without a sort, matches are in match id order, which is the order they were added in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MatchOrder {
+    sort: Option<MatchSort>,
+    order: SortOrder,
+}
+
+impl MatchOrder {
+    pub fn position(&self, mch: &Match) -> MatchPosition {
+        MatchPosition {
+            key: self.sort.and_then(|sort| sort.key(mch)),
+            id: mch.id,
+        }
+    }
+
+    // Orders the indexes keep, so a page can be found without comparing every match
+    pub fn indexed(&self) -> Option<IndexedOrder> {
+        match self.sort {
+            None => Some(IndexedOrder::Id),
+            Some(MatchSort::Date) => Some(IndexedOrder::Date(self.order)),
+            Some(_) => None,
+        }
+    }
+
+    fn name(&self) -> String {
+        format!(
+            "{}:{}",
+            self.sort.map(|sort| sort.name()).unwrap_or("id"),
+            match self.order {
+                SortOrder::Asc => "asc",
+                SortOrder::Desc => "desc",
+            }
+        )
+    }
+
+    pub fn compare(&self, a: &MatchPosition, b: &MatchPosition) -> Ordering {
+        let key_order = match (a.key, b.key, self.order) {
+            (Some(a), Some(b), SortOrder::Asc) => a.cmp(&b),
+            (Some(a), Some(b), SortOrder::Desc) => b.cmp(&a),
+            (a, b, _) => a.is_none().cmp(&b.is_none()),
+        };
+
+        key_order.then(a.id.cmp(&b.id))
+    }
+
+    pub fn compare_matches(&self, a: &Match, b: &Match) -> Ordering {
+        self.compare(&self.position(a), &self.position(b))
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CursorDirection {
+    Next,
+    Prev,
+}
+
+// Opaque page token. It points at the last match of a page for the next one and at
+// the first match for the previous one. Match ids change when the database is rebuilt,
+// so the match is named by its key too and pages go on from wherever it is now.
+// Only when the match is gone, the position it had is used as is.
+// It only works with the sort and order it was made for.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MatchCursor {
+    pub direction: CursorDirection,
+    pub position: MatchPosition,
+    pub mch: MatchKey,
+}
+
+impl MatchCursor {
+    pub fn encode(&self, order: &MatchOrder) -> String {
+        // Serializing strings and numbers can't fail
+        let text = serde_json::to_string(&(order.name(), self)).unwrap_or_default();
+
+        URL_SAFE_NO_PAD.encode(text)
+    }
+
+    // Cursors that don't decode or were made for another sort are rejected
+    pub fn decode(cursor: &str, order: &MatchOrder) -> Option<Self> {
+        let text = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let (order_name, cursor) = serde_json::from_slice::<(String, Self)>(&text).ok()?;
+
+        (order_name == order.name()).then_some(cursor)
+    }
+}
+
+// Pages come either from offset and per_page or from limit and cursor, not a mix of them.
+#[derive(Clone, Deserialize, Default, Debug)]
 pub struct QueryParams {
     pub offset: Option<usize>,
     pub per_page: Option<PagPerPage>,
+    pub limit: Option<usize>,
+    pub cursor: Option<String>,
+    pub sort: Option<MatchSort>,
+    pub order: Option<SortOrder>,
     pub home_away: Option<HomeAwayOption>,
+    pub status: Option<MatchStatus>,
+    pub exclude_status: Option<MatchStatus>,
+    pub phase: Option<Phase>,
+    pub round_of: Option<RoundOf>,
+    pub matchday_start: Option<Matchday>,
+    pub matchday_end: Option<Matchday>,
+    // IANA timezone name like 'America/New_York' that kickoffs are converted to
+    pub tz: Option<Tz>,
+    // Date filters, days in 'from' and 'to' are included
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub weekdays: Option<Weekdays>,
+    // Local kickoff time window like '19:00' to '21:00'. A window like '22:00' to '02:00'
+    // wraps around midnight. Matches without a kickoff time are left out.
+    pub time_from: Option<NaiveTime>,
+    pub time_to: Option<NaiveTime>,
+    // Score filters, matches without the score of the period are left out
+    pub period: Option<ScorePeriod>,
+    pub min_goals: Option<u8>,
+    pub max_goals: Option<u8>,
+    pub min_margin: Option<u8>,
+    pub max_margin: Option<u8>,
+    pub result: Option<MatchResult>,
+    pub score: Option<ScoreLine>,
+    // Half time leader lost the match. Needs both half time and full time scores.
+    pub comeback: Option<bool>,
+}
+
+impl QueryParams {
+    pub fn match_order(&self) -> MatchOrder {
+        MatchOrder {
+            sort: self.sort,
+            order: self.order.unwrap_or_default(),
+        }
+    }
+
+    pub fn uses_cursor(&self) -> bool {
+        self.limit.is_some() || self.cursor.is_some()
+    }
+
+    pub fn has_match_filters(&self) -> bool {
+        self.status.is_some()
+            || self.exclude_status.is_some()
+            || self.phase.is_some()
+            || self.round_of.is_some()
+            || self.matchday_start.is_some()
+            || self.matchday_end.is_some()
+            || self.from.is_some()
+            || self.to.is_some()
+            || self.weekdays.is_some()
+            || self.time_from.is_some()
+            || self.time_to.is_some()
+            || self.has_score_filters()
+    }
+
+    fn has_score_filters(&self) -> bool {
+        self.min_goals.is_some()
+            || self.max_goals.is_some()
+            || self.min_margin.is_some()
+            || self.max_margin.is_some()
+            || self.result.is_some()
+            || self.score.is_some()
+            || self.comeback.is_some()
+    }
+
+    pub fn filter_match(&self, mch: &Match) -> bool {
+        self.status.is_none_or(|status| mch.status == status)
+            && self
+                .exclude_status
+                .is_none_or(|status| mch.status != status)
+            && self.phase.is_none_or(|phase| mch.round_info.phase == phase)
+            && self
+                .round_of
+                .is_none_or(|round_of| mch.round_info.round_of == Some(round_of))
+            && self.matchday_start.is_none_or(|start| {
+                mch.round_info
+                    .matchday
+                    .is_some_and(|matchday| matchday >= start)
+            })
+            && self.matchday_end.is_none_or(|end| {
+                mch.round_info
+                    .matchday
+                    .is_some_and(|matchday| matchday <= end)
+            })
+            && self.from.is_none_or(|date| mch.date >= date)
+            && self.to.is_none_or(|date| mch.date <= date)
+            && self
+                .weekdays
+                .is_none_or(|weekdays| weekdays.contains(mch.date.weekday()))
+            && self.filter_time(mch)
+            && self.filter_score(mch)
+    }
+
+    fn filter_time(&self, mch: &Match) -> bool {
+        if self.time_from.is_none() && self.time_to.is_none() {
+            return true;
+        }
+
+        let Some(time) = mch.time else {
+            return false;
+        };
+
+        match (self.time_from, self.time_to) {
+            (Some(from), Some(to)) if to < from => time >= from || time <= to,
+            (from, to) => from.is_none_or(|from| time >= from) && to.is_none_or(|to| time <= to),
+        }
+    }
+
+    fn filter_score(&self, mch: &Match) -> bool {
+        if !self.has_score_filters() {
+            return true;
+        }
+
+        let period_score = match self.period.unwrap_or_default() {
+            ScorePeriod::Ft => mch.score.full_time.as_ref(),
+            ScorePeriod::Ht => mch.score.half_time.as_ref(),
+        };
+
+        let score_matches = |score: &ScoreGoals| {
+            self.min_goals.is_none_or(|goals| score.total() >= goals)
+                && self.max_goals.is_none_or(|goals| score.total() <= goals)
+                && self
+                    .min_margin
+                    .is_none_or(|margin| score.margin() >= margin)
+                && self
+                    .max_margin
+                    .is_none_or(|margin| score.margin() <= margin)
+                && self.result.is_none_or(|result| score.result() == result)
+                && self
+                    .score
+                    .is_none_or(|ScoreLine(goals1, goals2)| *score == ScoreGoals(goals1, goals2))
+        };
+
+        let comeback_matches = self.comeback.is_none_or(|comeback| {
+            let (Some(half_time), Some(full_time)) = (&mch.score.half_time, &mch.score.full_time)
+            else {
+                return false;
+            };
+
+            let is_comeback = matches!(
+                (half_time.result(), full_time.result()),
+                (MatchResult::Home, MatchResult::Away) | (MatchResult::Away, MatchResult::Home)
+            );
+            is_comeback == comeback
+        });
+
+        period_score.is_some_and(score_matches) && comeback_matches
+    }
+}
+
+// Criteria of the /matches route. Home and away, the status and dates come from `QueryParams`.
+#[derive(Copy, Clone, Deserialize, Default, Debug)]
+pub struct MatchQueryParams {
+    pub team: Option<TeamId>,
+    pub opponent: Option<TeamId>,
+    pub tournament: Option<TournamentId>,
+    pub season: Option<SeasonId>,
+    pub year_start: Option<Year>,
+    pub year_end: Option<Year>,
+}
+
+impl MatchQueryParams {
+    pub fn query(&self) -> MatchQuery {
+        let mut query = MatchQuery::new();
+
+        if let Some(team_id) = self.team {
+            query = query.team(team_id);
+        }
+        if let Some(team_id) = self.opponent {
+            query = query.opponent(team_id);
+        }
+        if let Some(tour_id) = self.tournament {
+            query = query.tournament(tour_id);
+        }
+        if let Some(season_id) = self.season {
+            query = query.season(season_id);
+        }
+
+        query_year_range(query, self.year_start, self.year_end)
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Default, Debug)]
+pub struct HeadToHeadQueryParams {
+    pub tournament: Option<TournamentId>,
+    pub year_start: Option<Year>,
+    pub year_end: Option<Year>,
+}
+
+impl HeadToHeadQueryParams {
+    pub fn query(&self, team_id: TeamId, other_id: TeamId) -> MatchQuery {
+        let mut query = MatchQuery::new().team(team_id).opponent(other_id);
+
+        if let Some(tour_id) = self.tournament {
+            query = query.tournament(tour_id);
+        }
+
+        query_year_range(query, self.year_start, self.year_end)
+    }
+}
+
+// A single year when only one end of the range is given
+fn query_year_range(
+    query: MatchQuery,
+    year_start: Option<Year>,
+    year_end: Option<Year>,
+) -> MatchQuery {
+    match (year_start, year_end) {
+        (Some(year_start), Some(year_end)) => query.year_range(year_start, year_end),
+        (Some(year), None) | (None, Some(year)) => query.year(year),
+        (None, None) => query,
+    }
+}
+
+#[derive(Copy, Clone, Deserialize, Default, Debug)]
+pub struct ScorerQueryParams {
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Deserialize, Debug)]
+pub struct SearchQueryParams {
+    pub q: String,
+    pub kind: Option<SearchKind>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Clone, Deserialize, Default, Debug)]
+pub struct TournamentQueryParams {
+    pub region: Option<String>,
+    pub kind: Option<CompetitionKind>,
+    pub tier: Option<u8>,
+}
+
+impl TournamentQueryParams {
+    pub fn is_empty(&self) -> bool {
+        self.region.is_none() && self.kind.is_none() && self.tier.is_none()
+    }
+
+    pub fn matches(&self, meta: &TournamentMeta) -> bool {
+        self.region
+            .as_ref()
+            .is_none_or(|region| meta.region.eq_ignore_ascii_case(region))
+            && self.kind.is_none_or(|kind| meta.kind == kind)
+            && self.tier.is_none_or(|tier| meta.tier == Some(tier))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cursor(direction: CursorDirection, key: Option<i64>) -> MatchCursor {
+        MatchCursor {
+            direction,
+            position: MatchPosition { key, id: 7 },
+            mch: MatchKey {
+                date: NaiveDate::from_ymd_opt(2024, 8, 17).unwrap(),
+                tournament: "English Premier League".to_string(),
+                team1: "Arsenal FC".to_string(),
+                team2: "Chelsea FC".to_string(),
+            },
+        }
+    }
+
+    fn order(sort: Option<MatchSort>, order: SortOrder) -> MatchOrder {
+        MatchOrder { sort, order }
+    }
+
+    #[test]
+    fn cursors_decode_to_what_was_encoded() {
+        for (direction, match_order, key) in [
+            (CursorDirection::Next, order(None, SortOrder::Asc), None),
+            (
+                CursorDirection::Prev,
+                order(Some(MatchSort::Kickoff), SortOrder::Desc),
+                Some(1_723_914_000),
+            ),
+            (
+                CursorDirection::Next,
+                order(Some(MatchSort::Goals), SortOrder::Asc),
+                None,
+            ),
+        ] {
+            let encoded = cursor(direction, key).encode(&match_order);
+            assert!(!encoded.contains(['+', '/', '=']));
+
+            let decoded = MatchCursor::decode(&encoded, &match_order).unwrap();
+            assert_eq!(decoded.direction, direction);
+            assert_eq!(decoded.position, MatchPosition { key, id: 7 });
+            assert_eq!(decoded.mch, cursor(direction, key).mch);
+        }
+    }
+
+    #[test]
+    fn cursors_only_decode_for_their_sort() {
+        let date_asc = order(Some(MatchSort::Date), SortOrder::Asc);
+        let encoded = cursor(CursorDirection::Next, Some(739_115)).encode(&date_asc);
+
+        assert!(
+            MatchCursor::decode(&encoded, &order(Some(MatchSort::Date), SortOrder::Desc)).is_none()
+        );
+        assert!(MatchCursor::decode(&encoded, &order(None, SortOrder::Asc)).is_none());
+        assert!(MatchCursor::decode(&encoded[1..], &date_asc).is_none());
+        assert!(MatchCursor::decode("not a cursor", &date_asc).is_none());
+    }
+
+    #[test]
+    fn ties_are_broken_by_match_id() {
+        let position = |key, id| MatchPosition { key, id };
+
+        let goals_desc = order(Some(MatchSort::Goals), SortOrder::Desc);
+        assert_eq!(
+            goals_desc.compare(&position(Some(4), 2), &position(Some(4), 5)),
+            Ordering::Less
+        );
+        assert_eq!(
+            goals_desc.compare(&position(Some(4), 5), &position(Some(3), 2)),
+            Ordering::Less
+        );
+        // Matches without a key go last in both orders
+        assert_eq!(
+            goals_desc.compare(&position(None, 1), &position(Some(0), 9)),
+            Ordering::Greater
+        );
+
+        let goals_asc = order(Some(MatchSort::Goals), SortOrder::Asc);
+        assert_eq!(
+            goals_asc.compare(&position(None, 1), &position(Some(9), 9)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            goals_asc.compare(&position(None, 1), &position(None, 1)),
+            Ordering::Equal
+        );
+    }
 }
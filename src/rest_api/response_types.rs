@@ -1,9 +1,25 @@
+use std::borrow::Cow;
+
 use serde::Serialize;
 
-use crate::imdb::data_types::Match;
+use crate::imdb::data_types::{HeadToHead, Match};
 
+// Matches are only copied when they're changed for the response,
+// like when kickoffs are converted to another timezone.
 #[derive(Serialize)]
 pub struct MatchListResponse<'a> {
     pub total: usize,
-    pub list: Vec<&'a Match>,
+    pub list: Vec<Cow<'a, Match>>,
+    // Cursors of the pages around this one, only with cursor paging
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+// Summary covers every meeting in the tournament and years asked for,
+// the list is filtered and paged like any other match list.
+#[derive(Serialize)]
+pub struct HeadToHeadResponse<'a> {
+    pub summary: HeadToHead<'a>,
+    #[serde(flatten)]
+    pub matches: MatchListResponse<'a>,
 }
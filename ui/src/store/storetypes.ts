@@ -18,9 +18,25 @@ export type Season = {
   end_year: number,
 }
 
+export enum CompetitionKind {
+  League = "league",
+  Cup = "cup",
+  Continental = "continental",
+}
+
+export type TournamentMeta = {
+  code: string,
+  region: string,
+  kind: CompetitionKind,
+  tier?: number,
+  timezone?: string,
+}
+
 export type Tournament = {
   id: number,
   name: string,
+  country?: string,
+  meta?: TournamentMeta,
 }
 
 export type Team = {
@@ -28,9 +44,140 @@ export type Team = {
   name: string,
 }
 
+export type Venue = {
+  id: number,
+  name: string,
+  matches: number,
+  home_teams: Team[],
+}
+
+export type Goal = {
+  player_id: number,
+  player: string,
+  team: 1 | 2,
+  minute?: number,
+  offset?: number,
+  penalty: boolean,
+  own_goal: boolean,
+}
+
+export type Player = {
+  id: number,
+  name: string,
+  team_id: number,
+}
+
+export type Scorer = {
+  player: Player,
+  team: Team,
+  goals: number,
+  penalties: number,
+}
+
+export type PlayerGoals = {
+  player: Player,
+  team: Team,
+  goals: number,
+  own_goals: number,
+  list: { match: Match, goals: Goal[] }[],
+}
+
+export type FormMatch = {
+  match_id: number,
+  date: string,
+  result: "win" | "draw" | "loss",
+}
+
+export type LeaguePosition = {
+  position: number,
+  played: number,
+  points: number,
+  goal_difference: number,
+}
+
+export type MatchTeamContext = {
+  team: Team,
+  form: FormMatch[],
+  position: LeaguePosition | null,
+}
+
+export type MatchDetail = {
+  match: Match,
+  tournament: Tournament,
+  season: Season,
+  teams: [MatchTeamContext, MatchTeamContext],
+  previous_meeting: Match | null,
+  next_meeting: Match | null,
+}
+
+export type SearchHit = {
+  kind: "team" | "tournament",
+  id: number,
+  name: string,
+  country: string | null,
+  alias: string | null,
+}
+
+export type HeadToHeadSide = {
+  team: Team,
+  wins: number,
+  draws: number,
+  losses: number,
+  goals_for: number,
+  goals_against: number,
+  biggest_win: Match | null,
+}
+
+export type HeadToHead = {
+  summary: {
+    played: number,
+    sides: [HeadToHeadSide, HeadToHeadSide],
+    first_meeting: Match | null,
+    last_meeting: Match | null,
+  },
+  total: number,
+  list: Match[],
+  next: string | null,
+  prev: string | null,
+}
+
+export enum DecidedBy {
+  Regulation = "regulation",
+  ExtraTime = "extra_time",
+  Penalties = "penalties",
+}
+
 export type Score = {
   half_time?: [number, number],
   full_time?: [number, number],
+  extra_time?: [number, number],
+  penalties?: [number, number],
+  decided_by?: DecidedBy,
+}
+
+export enum MatchStatus {
+  Regular = "regular",
+  Postponed = "postponed",
+  Cancelled = "cancelled",
+  Awarded = "awarded",
+  Abandoned = "abandoned",
+  Unknown = "unknown",
+}
+
+export enum Phase {
+  Qualifying = "qualifying",
+  League = "league",
+  Playoffs = "playoffs",
+  Knockout = "knockout",
+  Final = "final",
+}
+
+export type RoundInfo = {
+  phase: Phase,
+  round_of?: number,
+  round_number?: number,
+  matchday?: number,
+  group?: string,
 }
 
 export type Match = {
@@ -41,10 +188,16 @@ export type Match = {
   round?: string,
   date: string,
   time?: string,
+  kickoff?: string,
   team1: string,
   team2: string,
   score: Score,
   stage?: string,
+  venue?: string,
+  venue_id?: number,
+  goals: Goal[],
+  status: MatchStatus,
+  round_info: RoundInfo,
 }
 
 export type FilterSeason = {